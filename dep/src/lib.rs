@@ -80,7 +80,7 @@ pub enum Config {
 }
 
 #[repr(i32)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Safety {
     Off = 0,
     Normal = 1,
@@ -115,7 +115,7 @@ pub enum Info {
 }
 
 #[repr(i32)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Seek {
     LEFast = -2,
     LE = -1,
@@ -141,9 +141,12 @@ extern "C" {
     pub fn lsm_get_user_version(db: *mut lsm_db, version: *mut u32) -> Error;
     pub fn lsm_set_user_version(db: *mut lsm_db, version: u32) -> Error;
 
+    /// Opens nested transactions until `level` of them are open.
     pub fn lsm_begin(db: *mut lsm_db, level: NonZeroU32) -> Error;
-    pub fn lsm_commit(db: *mut lsm_db, level: NonZeroU32) -> Error;
-    pub fn lsm_rollback(db: *mut lsm_db, level: NonZeroU32) -> Error;
+    /// Commits nested transactions until only `level` of them remain open; `0` commits to disk.
+    pub fn lsm_commit(db: *mut lsm_db, level: u32) -> Error;
+    /// Rolls back to the point where transaction `level` was opened, leaving it open; `0` closes them all.
+    pub fn lsm_rollback(db: *mut lsm_db, level: u32) -> Error;
 
     pub fn lsm_insert(
         db: *mut lsm_db,
@@ -0,0 +1,224 @@
+use crate::{Error, Map, Tree};
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// A set of insertions and deletions that are applied to a `Map` atomically, by [`Map::write`].
+///
+/// Operations are kept sorted and deduplicated as they are added: a later write to a key replaces
+/// any earlier one, and a range deletion drops the writes it covers that were added before it.
+/// Range deletions are applied first, followed by the individual writes in key order.
+///
+/// ```no_run
+/// # fn example(map: &mut lsm1::Map) -> Result<(), lsm1::Error> {
+/// use lsm1::WriteBatch;
+///
+/// let batch = WriteBatch::new()
+///     .put(b"key", b"value")
+///     .delete(b"other")
+///     .delete_range(b"a".as_ref()..b"b".as_ref());
+///
+/// map.write(batch)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteBatch {
+    ranges: Vec<(Bound<Vec<u8>>, Bound<Vec<u8>>)>,
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>, // `None` marks a deletion
+}
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets `key` to `value`.
+    pub fn put(mut self, key: &[u8], value: &[u8]) -> Self {
+        self.writes.insert(key.into(), Some(value.into()));
+        self
+    }
+
+    /// Removes `key`.
+    pub fn delete(mut self, key: &[u8]) -> Self {
+        self.writes.insert(key.into(), None);
+        self
+    }
+
+    /// Removes every key within `range`.
+    pub fn delete_range<'r>(mut self, range: impl std::ops::RangeBounds<&'r [u8]>) -> Self {
+        let start = range.start_bound().map(|key| key.to_vec());
+        let end = range.end_bound().map(|key| key.to_vec());
+        let range = (start, end);
+
+        self.writes
+            .retain(|key, _| std::ops::RangeBounds::contains(&range, key) == false);
+
+        if self.ranges.contains(&range) == false {
+            self.ranges.push(range);
+        }
+
+        self
+    }
+
+    /// Returns the number of operations in the batch.
+    pub fn len(&self) -> usize {
+        self.ranges.len() + self.writes.len()
+    }
+
+    /// Returns `true` if the batch contains no operations.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of key and value bytes held by the batch.
+    pub fn size_in_bytes(&self) -> usize {
+        let bound = |bound: &Bound<Vec<u8>>| match bound {
+            Bound::Included(key) | Bound::Excluded(key) => key.len(),
+            Bound::Unbounded => 0,
+        };
+
+        let ranges = self.ranges.iter();
+        let writes = self.writes.iter();
+
+        ranges
+            .map(|(start, end)| bound(start) + bound(end))
+            .sum::<usize>()
+            + writes
+                .map(|(key, value)| key.len() + value.as_ref().map_or(0, Vec::len))
+                .sum::<usize>()
+    }
+
+    /// Encodes the batch so that it can be stored or sent to another process.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.size_in_bytes() + 9 * self.len());
+
+        let slice = |bytes: &mut Vec<u8>, slice: &[u8]| {
+            bytes.extend_from_slice(&(slice.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(slice);
+        };
+
+        for range in &self.ranges {
+            bytes.push(Tag::DeleteRange as u8);
+            for bound in [&range.0, &range.1] {
+                match bound {
+                    Bound::Unbounded => bytes.push(Tag::Unbounded as u8),
+                    Bound::Included(key) => {
+                        bytes.push(Tag::Included as u8);
+                        slice(&mut bytes, key);
+                    }
+                    Bound::Excluded(key) => {
+                        bytes.push(Tag::Excluded as u8);
+                        slice(&mut bytes, key);
+                    }
+                }
+            }
+        }
+
+        for (key, value) in &self.writes {
+            match value {
+                Some(value) => {
+                    bytes.push(Tag::Put as u8);
+                    slice(&mut bytes, key);
+                    slice(&mut bytes, value);
+                }
+                None => {
+                    bytes.push(Tag::Delete as u8);
+                    slice(&mut bytes, key);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    /// Decodes a batch produced by [`to_bytes`](WriteBatch::to_bytes), failing with
+    /// [`Error::Corrupt`] if `bytes` is not one.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, Error> {
+        fn byte(bytes: &mut &[u8]) -> Result<u8, Error> {
+            let (&first, rest) = bytes.split_first().ok_or(Error::Corrupt)?;
+            *bytes = rest;
+            Ok(first)
+        }
+
+        fn slice<'b>(bytes: &mut &'b [u8]) -> Result<&'b [u8], Error> {
+            let len = bytes.get(..4).ok_or(Error::Corrupt)?;
+            let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+
+            let end = 4usize.checked_add(len).ok_or(Error::Corrupt)?;
+            let slice = bytes.get(4..end).ok_or(Error::Corrupt)?;
+            *bytes = &bytes[end..];
+            Ok(slice)
+        }
+
+        fn bound<'b>(bytes: &mut &'b [u8]) -> Result<Bound<&'b [u8]>, Error> {
+            match byte(bytes)? {
+                tag if tag == Tag::Unbounded as u8 => Ok(Bound::Unbounded),
+                tag if tag == Tag::Included as u8 => Ok(Bound::Included(slice(bytes)?)),
+                tag if tag == Tag::Excluded as u8 => Ok(Bound::Excluded(slice(bytes)?)),
+                _ => Err(Error::Corrupt),
+            }
+        }
+
+        let mut batch = WriteBatch::new();
+        while bytes.is_empty() == false {
+            batch = match byte(&mut bytes)? {
+                tag if tag == Tag::Put as u8 => {
+                    let key = slice(&mut bytes)?;
+                    batch.put(key, slice(&mut bytes)?)
+                }
+                tag if tag == Tag::Delete as u8 => batch.delete(slice(&mut bytes)?),
+                tag if tag == Tag::DeleteRange as u8 => {
+                    let start = bound(&mut bytes)?;
+                    batch.delete_range((start, bound(&mut bytes)?))
+                }
+                _ => return Err(Error::Corrupt),
+            };
+        }
+
+        Ok(batch)
+    }
+
+    pub(crate) fn apply(&self, tree: &Tree) -> Result<(), lsm_ext::Error> {
+        for (start, end) in &self.ranges {
+            let start = start.as_ref().map(Vec::as_slice);
+            let end = end.as_ref().map(Vec::as_slice);
+            tree.remove_range((start, end))?;
+        }
+
+        for (key, value) in &self.writes {
+            match value {
+                Some(value) => tree.insert(key, value)?,
+                None => tree.remove(key)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> FromIterator<(K, V)> for WriteBatch {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let batch = WriteBatch::new();
+        iter.into_iter().fold(batch, |batch, (key, value)| {
+            batch.put(key.as_ref(), value.as_ref())
+        })
+    }
+}
+
+#[repr(u8)]
+enum Tag {
+    Put = 0,
+    Delete = 1,
+    DeleteRange = 2,
+    Unbounded = 3,
+    Included = 4,
+    Excluded = 5,
+}
+
+impl Map {
+    /// Applies every operation in `batch` within a single transaction.
+    pub fn write(&mut self, batch: WriteBatch) -> Result<(), Error> {
+        self.transact(|tx| Ok(batch.apply(&tx.tree)?))
+    }
+}
@@ -0,0 +1,208 @@
+use lsm_ext::*;
+
+use crate::range::{Bound, Direction};
+use crate::Tree;
+
+/// A cursor over a `Map` that can be positioned on any key and moved in either direction.
+///
+/// A new cursor is not on any entry; position it with [`seek`](Cursor::seek), [`first`](Cursor::first)
+/// or [`last`](Cursor::last). The key and value it is on point directly into the database’s buffers,
+/// so they borrow the cursor and must be released before it is moved again.
+///
+/// ```compile_fail
+/// # fn scan(map: &lsm1::Map) -> Result<(), lsm1::Error> {
+/// let mut cursor = map.cursor();
+/// cursor.first()?;
+/// let key = cursor.key().unwrap();
+/// cursor.next()?; // `key` pointed into the entry the cursor just moved off of
+/// println!("{key:?}");
+/// # Ok(())
+/// # }
+/// ```
+pub struct Cursor<'m> {
+    bound: Bound<'m>,
+}
+
+impl<'m> Cursor<'m> {
+    pub(crate) fn new_in(tree: &'m Tree) -> Result<Self, Error> {
+        Ok(Cursor {
+            bound: Bound::open_in(tree)?,
+        })
+    }
+
+    /// Moves the cursor to `key`, or to the entry next to it that `seek` selects, returning whether
+    /// the cursor is on an entry.
+    ///
+    /// A cursor positioned with [`Seek::EQ`] cannot be moved with [`next`](Cursor::next) or
+    /// [`prev`](Cursor::prev), which fail with [`Error::Misuse`](crate::Error::Misuse).
+    ///
+    /// [`Seek::LEFast`] is refused with [`Error::Misuse`](crate::Error::Misuse), as it may leave the
+    /// cursor on a key that has been removed, whose value means nothing; use [`Seek::LE`] instead.
+    pub fn seek(&mut self, key: &[u8], seek: Seek) -> Result<bool, crate::Error> {
+        if seek == Seek::LEFast {
+            return Err(crate::Error::Misuse);
+        }
+
+        Ok(self.bound.seek(key, seek)?)
+    }
+
+    /// Moves the cursor to the first entry in the map, returning whether there is one.
+    pub fn first(&mut self) -> Result<bool, crate::Error> {
+        unsafe { lsm_csr_first(self.bound.raw()?).ok()? };
+        Ok(self.is_valid())
+    }
+
+    /// Moves the cursor to the last entry in the map, returning whether there is one.
+    pub fn last(&mut self) -> Result<bool, crate::Error> {
+        unsafe { lsm_csr_last(self.bound.raw()?).ok()? };
+        Ok(self.is_valid())
+    }
+
+    /// Moves the cursor to the following entry, returning whether there is one.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<bool, crate::Error> {
+        if self.is_valid() {
+            unsafe { lsm_csr_next(self.bound.raw()?).ok()? };
+        }
+
+        Ok(self.is_valid())
+    }
+
+    /// Moves the cursor to the preceding entry, returning whether there is one.
+    pub fn prev(&mut self) -> Result<bool, crate::Error> {
+        if self.is_valid() {
+            unsafe { lsm_csr_prev(self.bound.raw()?).ok()? };
+        }
+
+        Ok(self.is_valid())
+    }
+
+    #[inline]
+    /// Returns `true` if the cursor is on an entry.
+    pub fn is_valid(&self) -> bool {
+        self.bound.is_valid()
+    }
+
+    #[inline]
+    /// Returns the key of the entry under the cursor.
+    pub fn key(&self) -> Option<&[u8]> {
+        self.bound.key().ok()
+    }
+
+    #[inline]
+    /// Returns the value of the entry under the cursor.
+    pub fn value(&self) -> Option<&[u8]> {
+        self.bound.val().ok()
+    }
+
+    /// Returns a copy of the entry following the one under the cursor, without moving it.
+    pub fn peek_next(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>, crate::Error> {
+        self.peek(Direction::Next)
+    }
+
+    /// Returns a copy of the entry preceding the one under the cursor, without moving it.
+    pub fn peek_prev(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>, crate::Error> {
+        self.peek(Direction::Prev)
+    }
+
+    fn peek(&self, direction: Direction) -> Result<Option<(Vec<u8>, Vec<u8>)>, crate::Error> {
+        match self.key() {
+            // a second cursor leaves this one where it is, whatever mode it was positioned with
+            Some(key) => self
+                .bound
+                .tree()
+                .nearest(std::ops::Bound::Excluded(key), direction),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A cursor over a `Map` that can also modify it.
+///
+/// It dereferences to a [`Cursor`] for navigation. Each modification reopens the cursor, so that it
+/// sees the write, and re-seeks it, so that it keeps its place: it stays on the same entry after an
+/// insertion or a replacement, and moves on to the following entry after a removal.
+pub struct CursorMut<'m>(Cursor<'m>);
+
+impl<'m> CursorMut<'m> {
+    pub(crate) fn new_in(tree: &'m Tree) -> Result<Self, Error> {
+        Ok(CursorMut(Cursor::new_in(tree)?))
+    }
+
+    /// Inserts an entry that sorts between the previous entry and the one under the cursor.
+    ///
+    /// Fails with [`Error::Misuse`](crate::Error::Misuse) if `key` does not.
+    pub fn insert_before(&mut self, key: &[u8], value: &[u8]) -> Result<(), crate::Error> {
+        if let Some(current) = self.key() {
+            let prev = self.peek_prev()?;
+            if key >= current || prev.is_some_and(|(prev, _)| key <= prev.as_slice()) {
+                return Err(crate::Error::Misuse);
+            }
+        }
+
+        self.write(|tree| tree.insert(key, value))
+    }
+
+    /// Inserts an entry that sorts between the one under the cursor and the next entry.
+    ///
+    /// Fails with [`Error::Misuse`](crate::Error::Misuse) if `key` does not.
+    pub fn insert_after(&mut self, key: &[u8], value: &[u8]) -> Result<(), crate::Error> {
+        if let Some(current) = self.key() {
+            let next = self.peek_next()?;
+            if key <= current || next.is_some_and(|(next, _)| key >= next.as_slice()) {
+                return Err(crate::Error::Misuse);
+            }
+        }
+
+        self.write(|tree| tree.insert(key, value))
+    }
+
+    /// Replaces the value of the entry under the cursor.
+    pub fn replace_value(&mut self, value: &[u8]) -> Result<(), crate::Error> {
+        let key = self.key().ok_or(crate::Error::Misuse)?.to_vec();
+        self.write(|tree| tree.insert(&key, value))
+    }
+
+    /// Removes the entry under the cursor, returning it and moving the cursor to the following entry.
+    pub fn remove_current(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, crate::Error> {
+        let (Some(key), Some(value)) = (self.key(), self.value()) else {
+            return Ok(None);
+        };
+
+        let entry = (key.to_vec(), value.to_vec());
+        self.write(|tree| tree.remove(&entry.0))?;
+        Ok(Some(entry))
+    }
+
+    fn write(&mut self, f: impl FnOnce(&Tree) -> Result<(), Error>) -> Result<(), crate::Error> {
+        let tree = self.0.bound.tree();
+        let key = self.key().map(<[u8]>::to_vec);
+
+        // the cursor is closed for the write, and reopened on the snapshot that includes it
+        self.0.bound.unload();
+        f(tree)?;
+        self.0.bound = Bound::open_in(tree)?;
+
+        if let Some(key) = key {
+            self.seek(&key, Seek::GE)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'m> std::ops::Deref for CursorMut<'m> {
+    type Target = Cursor<'m>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Cursor<'m> {
+        &self.0
+    }
+}
+
+impl<'m> std::ops::DerefMut for CursorMut<'m> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Cursor<'m> {
+        &mut self.0
+    }
+}
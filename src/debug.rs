@@ -0,0 +1,92 @@
+use crate::info::take_string;
+use crate::{Error, Map, Tree};
+
+use lsm_ext::{lsm_info, Info};
+
+use std::ptr::null_mut;
+
+/// Low-level views of a database file, for looking into one that is suspected to be damaged, as
+/// returned by [`Map::debug`].
+///
+/// Every buffer LSM allocates for these is copied out and freed before returning.
+pub struct Introspection<'m> {
+    tree: &'m Tree,
+}
+
+/// An entry of the free-list: a block that can be reused once no reader needs the snapshot that
+/// freed it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Block {
+    pub block: u32,
+    pub snapshot: u64,
+}
+
+impl<'m> Introspection<'m> {
+    /// Dumps the contents of page `page`, as a hex dump if `hex` is set or as ASCII otherwise.
+    pub fn page(&self, page: u64, hex: bool) -> Result<String, Error> {
+        let info = match hex {
+            true => Info::PageHexDump,
+            false => Info::PageAsciiDump,
+        };
+
+        self.query(info, Some(page))
+    }
+
+    /// Lists the pages of the segment starting at page `first_page`, in order.
+    pub fn array_pages(&self, first_page: u64) -> Result<Vec<u64>, Error> {
+        let pages = self.query(Info::ArrayPages, Some(first_page))?;
+        pages
+            .split_whitespace()
+            .map(|page| page.parse().map_err(|_| Error::Corrupt))
+            .collect()
+    }
+
+    /// Lists the blocks on the free-list.
+    pub fn freelist(&self) -> Result<Vec<Block>, Error> {
+        let freelist = self.query(Info::Freelist, None)?;
+        parse_freelist(&freelist).ok_or(Error::Corrupt)
+    }
+
+    /// Queries one of the `lsm_info()` values reported as a string, passing `page` along if given.
+    fn query(&self, info: Info, page: Option<u64>) -> Result<String, Error> {
+        let mut ptr: *mut u8 = null_mut();
+        let out = &mut ptr as *mut *mut u8;
+        unsafe {
+            match page {
+                Some(page) => lsm_info(self.tree.db, info, page as i64, out),
+                None => lsm_info(self.tree.db, info, out),
+            }
+            .ok()?;
+
+            Ok(take_string(self.tree.db, ptr))
+        }
+    }
+}
+
+/// Parses a list of `{block snapshot}` entries.
+fn parse_freelist(freelist: &str) -> Option<Vec<Block>> {
+    let spaced = freelist.replace('{', " { ").replace('}', " } ");
+    let mut tokens = spaced.split_whitespace();
+
+    let mut blocks = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token != "{" {
+            return None;
+        }
+
+        blocks.push(Block {
+            block: tokens.next()?.parse().ok()?,
+            snapshot: tokens.next()?.parse().ok()?,
+        });
+        (tokens.next()? == "}").then_some(())?;
+    }
+
+    Some(blocks)
+}
+
+impl Map {
+    /// Gives access to page dumps and other low-level views of the database file.
+    pub fn debug(&self) -> Introspection<'_> {
+        Introspection { tree: &self.tree }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{Error, Map, Tree};
+
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+use std::ops::Bound;
+
+/// The number of entries read ahead at a time; no cursor is left open between batches, so the map
+/// can be written to while they are handed out.
+pub(crate) const BATCH: usize = 256;
+
+/// A forward scan over a range that runs inside its own transaction.
+struct Scan<'m> {
+    tree: &'m Tree,
+    level: Option<NonZeroU32>, // the transaction, until it is committed or rolled back
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+    buffer: VecDeque<(Vec<u8>, Vec<u8>)>,
+    exhausted: bool,
+}
+
+impl<'m> Scan<'m> {
+    fn new_in<'r>(
+        tree: &'m Tree,
+        range: impl std::ops::RangeBounds<&'r [u8]>,
+    ) -> Result<Self, Error> {
+        Ok(Scan {
+            level: Some(tree.begin()?),
+            tree,
+            start: range.start_bound().map(|key| key.to_vec()),
+            end: range.end_bound().map(|key| key.to_vec()),
+            buffer: VecDeque::new(),
+            exhausted: false,
+        })
+    }
+
+    fn next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        if self.buffer.is_empty() && self.exhausted == false {
+            let start = self.start.as_ref().map(Vec::as_slice);
+            let end = self.end.as_ref().map(Vec::as_slice);
+
+            let mut range = self.tree.range((start, end))?;
+            while self.buffer.len() < BATCH {
+                match range.try_next()? {
+                    Some(entry) => self.buffer.push_back(entry),
+                    None => {
+                        self.exhausted = true;
+                        break;
+                    }
+                }
+            }
+
+            if let Some((key, _)) = self.buffer.back() {
+                self.start = Bound::Excluded(key.clone());
+            }
+        }
+
+        Ok(self.buffer.pop_front())
+    }
+
+    /// Commits the transaction; if that fails, it is left open to be rolled back on drop.
+    fn commit(&mut self) -> Result<(), Error> {
+        if let Some(level) = self.level {
+            self.tree.commit(level)?;
+            self.level = None;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'m> Drop for Scan<'m> {
+    fn drop(&mut self) {
+        if let Some(level) = self.level.take() {
+            let _ = self.tree.rollback(level);
+        }
+    }
+}
+
+/// An iterator that removes a range of entries from a `Map`, yielding them as it goes.
+///
+/// Every entry in the range is removed once the iterator is dropped, including those it did not get
+/// to, all within a single transaction. Call [`finish`](Drain::finish) to find out whether that
+/// succeeded; if the iterator is dropped while panicking, the map is left untouched.
+pub struct Drain<'m> {
+    scan: Scan<'m>,
+    range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
+}
+
+impl<'m> Drain<'m> {
+    pub(crate) fn new_in<'r>(
+        tree: &'m Tree,
+        range: impl std::ops::RangeBounds<&'r [u8]>,
+    ) -> Result<Self, Error> {
+        let scan = Scan::new_in(tree, range)?;
+        let range = (scan.start.clone(), scan.end.clone());
+
+        Ok(Drain { scan, range })
+    }
+
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.scan.next()
+    }
+
+    /// Removes the whole range and commits the removal.
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.remove()
+    }
+
+    fn remove(&mut self) -> Result<(), Error> {
+        if self.scan.level.is_some() {
+            let start = self.range.0.as_ref().map(Vec::as_slice);
+            let end = self.range.1.as_ref().map(Vec::as_slice);
+
+            self.scan.tree.remove_range((start, end))?;
+            self.scan.commit()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'m> Iterator for Drain<'m> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl<'m> Drop for Drain<'m> {
+    fn drop(&mut self) {
+        if std::thread::panicking() == false {
+            let _ = self.remove();
+        }
+    }
+}
+
+/// An iterator that removes the entries of a range of a `Map` that match a predicate, yielding them.
+///
+/// Entries are removed as they are yielded, and entries the iterator does not get to are kept. The
+/// removals are committed as a single transaction once the iterator is dropped, or by
+/// [`finish`](ExtractIf::finish), which reports whether that succeeded; if the iterator is dropped
+/// while panicking, the map is left untouched.
+pub struct ExtractIf<'m, F> {
+    scan: Scan<'m>,
+    pred: F,
+}
+
+impl<'m, F> ExtractIf<'m, F>
+where
+    F: FnMut(&[u8], &[u8]) -> bool,
+{
+    pub(crate) fn new_in<'r>(
+        tree: &'m Tree,
+        range: impl std::ops::RangeBounds<&'r [u8]>,
+        pred: F,
+    ) -> Result<Self, Error> {
+        Ok(ExtractIf {
+            scan: Scan::new_in(tree, range)?,
+            pred,
+        })
+    }
+
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        while let Some((key, value)) = self.scan.next()? {
+            if (self.pred)(&key, &value) {
+                self.scan.tree.remove(&key)?;
+                return Ok(Some((key, value)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Commits the removals made so far.
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.scan.commit()
+    }
+}
+
+impl<'m, F> Iterator for ExtractIf<'m, F>
+where
+    F: FnMut(&[u8], &[u8]) -> bool,
+{
+    type Item = (Vec<u8>, Vec<u8>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl<'m, F> Drop for ExtractIf<'m, F> {
+    fn drop(&mut self) {
+        if std::thread::panicking() == false {
+            let _ = self.scan.commit();
+        }
+    }
+}
+
+impl Map {
+    #[inline]
+    /// Removes the entries within `range`, returning them in ascending key order.
+    ///
+    /// The whole range is removed, as a single transaction, once the returned iterator is dropped,
+    /// whether or not it was run to the end.
+    pub fn drain<'r, R: std::ops::RangeBounds<&'r [u8]>>(&mut self, range: R) -> Drain<'_> {
+        self.try_drain(range).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`drain`](Map::drain).
+    pub fn try_drain<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &mut self,
+        range: R,
+    ) -> Result<Drain<'_>, Error> {
+        Drain::new_in(&self.tree, range)
+    }
+
+    #[inline]
+    /// Creates an iterator that visits the entries within `range` in ascending key order, removing
+    /// and yielding those for which `pred` returns `true`.
+    ///
+    /// The removals are committed as a single transaction once the returned iterator is dropped.
+    pub fn extract_if<'r, R, F>(&mut self, range: R, pred: F) -> ExtractIf<'_, F>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+        F: FnMut(&[u8], &[u8]) -> bool,
+    {
+        self.try_extract_if(range, pred).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`extract_if`](Map::extract_if).
+    pub fn try_extract_if<'r, R, F>(&mut self, range: R, pred: F) -> Result<ExtractIf<'_, F>, Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+        F: FnMut(&[u8], &[u8]) -> bool,
+    {
+        ExtractIf::new_in(&self.tree, range, pred)
+    }
+
+    /// Visits the entries within `range` in ascending key order with no cursor left open in between,
+    /// so that `f` is free to write to the map, all within a single transaction.
+    pub(crate) fn scan<'r, R, F>(&mut self, range: R, mut f: F) -> Result<(), Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+        F: FnMut(&Tree, Vec<u8>, Vec<u8>) -> Result<(), Error>,
+    {
+        let mut scan = Scan::new_in(&self.tree, range)?;
+        while let Some((key, value)) = scan.next()? {
+            f(scan.tree, key, value)?;
+        }
+
+        scan.commit()
+    }
+
+    /// Removes the entries within `range` a batch at a time, handing each batch to `f` in ascending
+    /// key order before it is removed.
+    ///
+    /// Every batch is read, handed out and removed within a transaction of its own, so nothing
+    /// another connection commits in the meantime is removed without having been handed to `f`;
+    /// `f` must not write to this file through another connection, as it would find it locked.
+    pub(crate) fn drain_batches<'r, R, F>(&mut self, range: R, mut f: F) -> Result<(), Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+        F: FnMut(Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), Error>,
+    {
+        let mut start = range.start_bound().map(|key| key.to_vec());
+        let end = range.end_bound().map(|key| key.to_vec());
+
+        loop {
+            let first = start.as_ref().map(Vec::as_slice);
+            let mut scan = Scan::new_in(&self.tree, (first, end.as_ref().map(Vec::as_slice)))?;
+
+            let mut batch = Vec::with_capacity(BATCH);
+            while batch.len() < BATCH {
+                match scan.next()? {
+                    Some(entry) => batch.push(entry),
+                    None => break,
+                }
+            }
+
+            let exhausted = batch.len() < BATCH;
+            let Some(last) = batch.last().map(|(key, _)| key.clone()) else {
+                return scan.commit();
+            };
+
+            f(batch)?;
+            scan.tree
+                .remove_range((first, Bound::Included(last.as_slice())))?;
+            scan.commit()?;
+
+            if exhausted {
+                return Ok(());
+            }
+            start = Bound::Excluded(last);
+        }
+    }
+}
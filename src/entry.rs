@@ -4,7 +4,7 @@ use lsm_ext::*;
 ///
 /// This enum is constructed from the [entry] method.
 ///
-/// [entry]: Map::entry
+/// [entry]: crate::Map::entry
 ///
 pub enum Entry<'e> {
     Vacant(VacantEntry<'e>),
@@ -12,56 +12,88 @@ pub enum Entry<'e> {
 }
 
 impl<'e> Entry<'e> {
-    pub(crate) fn new_in(db: *mut lsm_db, key: &'e [u8]) -> Self {
-        let mut range = range::RangeBounds::new_in(db, key..).unwrap();
+    pub(crate) fn new_in(tree: &'e Tree, key: &'e [u8]) -> Result<Self, crate::Error> {
+        let bound = Bound::seek_in(tree, key, Seek::EQ)?;
 
-        match range.start_bound.key() {
-            Ok(found) if found == key => {
-                Entry::Occupied(OccupiedEntry(RefCell::new(range.start_bound)))
-            }
-            _ => Entry::Vacant(VacantEntry(range.start_bound, key)),
+        match bound.is_valid() {
+            true => Ok(Entry::Occupied(OccupiedEntry(bound))),
+            false => Ok(Entry::Vacant(VacantEntry(bound, key))),
         }
     }
 
     #[inline(always)]
     /// Ensures a value is in the entry by inserting the default if empty, and returns a reference to the value in the entry.
-    pub fn or_insert(self, default: &'e [u8]) -> &'e [u8] {
-        self.or_insert_with(|| default)
+    pub fn or_insert(self, default: &[u8]) -> ValueRef<'e> {
+        self.try_or_insert(default).unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`or_insert`](Entry::or_insert).
+    pub fn try_or_insert(self, default: &[u8]) -> Result<ValueRef<'e>, crate::Error> {
+        self.try_or_insert_with(|| default)
     }
 
     #[inline(always)]
     /// Ensures a value is in the entry by inserting the result of the default function if empty, and returns   reference to the value in the entry.
-    pub fn or_insert_with<F>(self, default: F) -> &'e [u8]
+    pub fn or_insert_with<F, V>(self, default: F) -> ValueRef<'e>
     where
-        F: FnOnce() -> &'e [u8],
+        F: FnOnce() -> V,
+        V: AsRef<[u8]>,
     {
-        self.or_insert_with_key(|_| default())
+        self.try_or_insert_with(default).unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`or_insert_with`](Entry::or_insert_with).
+    pub fn try_or_insert_with<F, V>(self, default: F) -> Result<ValueRef<'e>, crate::Error>
+    where
+        F: FnOnce() -> V,
+        V: AsRef<[u8]>,
+    {
+        self.try_or_insert_with_key(|_| default())
     }
 
     #[inline]
     /// Ensures a value is in the entry by inserting, if empty, the result of the default function. This method allows for generating key-derived values for insertion by providing the default function a reference to the key that was moved during the `.entry(key)` method call.
-    pub fn or_insert_with_key<F>(self, default: F) -> &'e [u8]
+    pub fn or_insert_with_key<F, V>(self, default: F) -> ValueRef<'e>
     where
-        F: FnOnce(&[u8]) -> &'e [u8],
+        F: FnOnce(&[u8]) -> V,
+        V: AsRef<[u8]>,
+    {
+        self.try_or_insert_with_key(default).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`or_insert_with_key`](Entry::or_insert_with_key).
+    pub fn try_or_insert_with_key<F, V>(self, default: F) -> Result<ValueRef<'e>, crate::Error>
+    where
+        F: FnOnce(&[u8]) -> V,
+        V: AsRef<[u8]>,
     {
         match self {
-            Entry::Occupied(entry) => entry.get(),
+            Entry::Occupied(entry) => entry.try_into_ref(),
             Entry::Vacant(entry) => {
                 let value = default(entry.1);
-                entry.insert(value)
+                entry.try_insert(value.as_ref())
             }
         }
     }
 
     #[inline(always)]
     /// Ensures a value is in the entry by inserting the default value if empty, and returns a reference to the value in the entry.
-    pub fn or_default(self) -> &'e [u8] {
-        self.or_insert(Default::default())
+    pub fn or_default(self) -> ValueRef<'e> {
+        self.try_or_default().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`or_default`](Entry::or_default).
+    pub fn try_or_default(self) -> Result<ValueRef<'e>, crate::Error> {
+        self.try_or_insert(Default::default())
     }
 
     #[inline(always)]
     /// Returns a reference to this entry’s key.
-    pub fn key(&self) -> &'e [u8] {
+    pub fn key(&self) -> &[u8] {
         match self {
             Entry::Occupied(entry) => entry.key(),
             Entry::Vacant(entry) => entry.key(),
@@ -72,34 +104,38 @@ impl<'e> Entry<'e> {
     /// Provides in-place mutable access to an occupied entry before any potential inserts into the map.
     pub fn and_modify<F>(self, modify: F) -> Entry<'e>
     where
-        F: FnOnce(&mut std::borrow::Cow<'e, [u8]>),
+        F: FnOnce(&mut std::borrow::Cow<'_, [u8]>),
     {
-        match &self {
-            Entry::Vacant(_) => self,
-            Entry::Occupied(occupied) => {
-                let mut entry = occupied.0.borrow_mut();
-                let val = entry.val().unwrap();
+        self.try_and_modify(modify).unwrap()
+    }
 
-                let mut value = std::borrow::Cow::Borrowed(val);
+    #[inline]
+    /// Fallible version of [`and_modify`](Entry::and_modify).
+    pub fn try_and_modify<F>(self, modify: F) -> Result<Entry<'e>, crate::Error>
+    where
+        F: FnOnce(&mut std::borrow::Cow<'_, [u8]>),
+    {
+        match self {
+            Entry::Vacant(_) => Ok(self),
+            Entry::Occupied(mut entry) => {
+                let mut value = std::borrow::Cow::Borrowed(entry.try_get()?);
                 modify(&mut value);
 
                 match value {
                     std::borrow::Cow::Borrowed(_) => {} // no changes were made
                     std::borrow::Cow::Owned(value) => {
-                        entry.replace(&value).unwrap();
+                        entry.try_insert(&value)?;
                     }
                 }
 
-                drop(entry);
-                self
+                Ok(Entry::Occupied(entry))
             }
         }
     }
 }
 
-use crate::range;
-use crate::range::Bound;
-use std::cell::RefCell;
+use crate::range::{Bound, Direction};
+use crate::{Tree, ValueRef};
 
 pub struct VacantEntry<'e>(Bound<'e>, &'e [u8]);
 
@@ -118,56 +154,113 @@ impl<'e> VacantEntry<'e> {
 
     #[inline(always)]
     /// Sets the value of the entry with the VacantEntry’s key, and returns a reference to it.
-    pub fn insert(self, value: &'e [u8]) -> &'e [u8] {
-        let mut bound = self.0;
-        bound.insert(self.1, value).unwrap();
-        value
+    pub fn insert(self, value: &[u8]) -> ValueRef<'e> {
+        self.try_insert(value).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`insert`](VacantEntry::insert).
+    pub fn try_insert(self, value: &[u8]) -> Result<ValueRef<'e>, crate::Error> {
+        let VacantEntry(bound, key) = self;
+        let tree = bound.tree();
+        drop(bound);
+
+        tree.insert(key, value)?;
+        let bound = Bound::new_in(tree, std::ops::Bound::Included(key), Direction::Next)?;
+        Ok(ValueRef::new_in(bound)?)
     }
 }
 
-pub struct OccupiedEntry<'e>(pub(crate) RefCell<Bound<'e>>);
+pub struct OccupiedEntry<'e>(pub(crate) Bound<'e>);
 
 impl<'e> OccupiedEntry<'e> {
     #[inline]
     /// Gets a reference to the key in the entry.
-    pub fn key(&self) -> &'e [u8] {
-        self.0.borrow_mut().key().unwrap()
+    pub fn key(&self) -> &[u8] {
+        self.try_key().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`key`](OccupiedEntry::key).
+    pub fn try_key(&self) -> Result<&[u8], crate::Error> {
+        Ok(self.0.key()?)
     }
 
     #[inline]
     /// Gets a reference to the value in the entry.
-    pub fn get(&self) -> &'e [u8] {
-        self.0.borrow_mut().val().unwrap()
+    pub fn get(&self) -> &[u8] {
+        self.try_get().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`get`](OccupiedEntry::get).
+    pub fn try_get(&self) -> Result<&[u8], crate::Error> {
+        Ok(self.0.val()?)
+    }
+
+    #[inline]
+    /// Converts the entry into a reference to its value.
+    pub fn into_ref(self) -> ValueRef<'e> {
+        self.try_into_ref().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`into_ref`](OccupiedEntry::into_ref).
+    pub fn try_into_ref(self) -> Result<ValueRef<'e>, crate::Error> {
+        Ok(ValueRef::new_in(self.0)?)
     }
 
     #[inline]
     /// Sets the value of the entry with the OccupiedEntry’s key, and returns the entry’s old value.
-    pub fn insert(&self, value: &'e [u8]) -> impl AsRef<[u8]> {
-        let mut entry = self.0.borrow_mut();
-        let old = entry.val().unwrap().to_vec();
-        entry.replace(value).unwrap();
+    pub fn insert(&mut self, value: &[u8]) -> Vec<u8> {
+        self.try_insert(value).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`insert`](OccupiedEntry::insert).
+    pub fn try_insert(&mut self, value: &[u8]) -> Result<Vec<u8>, crate::Error> {
+        let tree = self.0.tree();
+        let key = self.0.key()?.to_vec();
+        let old = self.0.val()?.to_vec();
+
+        self.0.unload();
+        tree.insert(&key, value)?;
 
-        old
+        // look the key up again, with a cursor that sees the write
+        self.0 = Bound::new_in(tree, std::ops::Bound::Included(&key), Direction::Next)?;
+
+        Ok(old)
     }
 
     #[inline]
     /// Takes the value of the entry out of the map, and returns it.
     pub fn remove(self) -> Vec<u8> {
-        let mut entry = self.0.borrow_mut();
-        let val = entry.val().unwrap().to_vec();
-        entry.remove().unwrap();
+        self.try_remove().unwrap()
+    }
 
-        val
+    #[inline]
+    /// Fallible version of [`remove`](OccupiedEntry::remove).
+    pub fn try_remove(self) -> Result<Vec<u8>, crate::Error> {
+        let (_, val) = self.try_remove_entry()?;
+        Ok(val)
     }
 
     #[inline]
     /// Take ownership of the key and value from the map.
     pub fn remove_entry(self) -> (Vec<u8>, Vec<u8>) {
-        let mut entry = self.0.borrow_mut();
-        let key = entry.key().unwrap().to_vec();
-        let val = entry.val().unwrap().to_vec();
-        entry.remove().unwrap();
+        self.try_remove_entry().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`remove_entry`](OccupiedEntry::remove_entry).
+    pub fn try_remove_entry(self) -> Result<(Vec<u8>, Vec<u8>), crate::Error> {
+        let entry = self.0;
+        let tree = entry.tree();
+        let key = entry.key()?.to_vec();
+        let val = entry.val()?.to_vec();
 
-        (key, val)
+        drop(entry);
+        tree.remove(&key)?;
+        Ok((key, val))
     }
 }
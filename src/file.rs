@@ -1,4 +1,4 @@
-use crate::Tree;
+use crate::{OpenOptions, Tree};
 
 extern crate lsm_ext;
 use lsm_ext::*;
@@ -8,14 +8,92 @@ use std::ptr::null_mut;
 
 impl Tree {
     pub fn new(path: &str) -> Result<Self, Error> {
+        Self::open(path, &OpenOptions::new())
+    }
+
+    pub fn open(path: &str, options: &OpenOptions) -> Result<Self, Error> {
         let mut db: *mut lsm_db = null_mut();
-        let path = CString::new(path).map_err(|_| Error::NoEnt)?;
+        let name = CString::new(path).map_err(|_| Error::NoEnt)?;
 
         unsafe {
             lsm_new(null_mut(), &mut db).ok()?;
-            lsm_open(db, path.as_ptr() as *const u8).ok()?;
+            let tree = Tree {
+                db,
+                path: path.to_owned(),
+                depth: Default::default(),
+                pins: Default::default(),
+                cache: Default::default(),
+            }; // closes the connection if opening fails
+
+            for (config, value) in options.before_open() {
+                tree.config(config, value)?;
+            }
+
+            lsm_open(tree.db, name.as_ptr() as *const u8).ok()?;
+
+            for (config, value) in options.after_open() {
+                tree.config(config, value)?;
+            }
+
+            Ok(tree)
         }
+    }
+
+    /// Sets a configuration parameter (or only queries it, if `value` is negative) and returns its effective value.
+    pub fn config(&self, config: Config, value: i32) -> Result<i32, Error> {
+        let mut value = value;
+        unsafe { lsm_config(self.db, config, &mut value as *mut i32).ok()? };
+
+        Ok(value)
+    }
+
+    /// The path the database was opened with.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
 
-        Ok(Tree { db })
+    /// Merges up to `kilobytes` KB of segments, `merge` or more at a time, returning the number of
+    /// KB written.
+    pub fn work(&self, merge: u32, kilobytes: u32) -> Result<u32, Error> {
+        let merge = std::num::NonZeroU32::new(merge).ok_or(Error::Misuse)?;
+        let mut written = 0;
+
+        self.reset();
+        unsafe { lsm_work(self.db, merge, kilobytes, &mut written).ok()? };
+        Ok(written.max(0) as u32)
+    }
+
+    /// Writes the in-memory tree out to the database file as a new segment.
+    pub fn flush(&self) -> Result<(), Error> {
+        self.reset();
+        unsafe { lsm_flush(self.db).ok() }
+    }
+
+    /// Syncs the database file and writes a checkpoint, returning the number of KB written to it
+    /// since the previous one.
+    pub fn checkpoint(&self) -> Result<u32, Error> {
+        let mut kilobytes = 0;
+
+        self.reset();
+        unsafe { lsm_checkpoint(self.db, &mut kilobytes).ok()? };
+        Ok(kilobytes)
+    }
+
+    /// Closes the connection, reporting any failure to checkpoint or release the database file.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.reset();
+        let db = std::mem::replace(&mut self.db, null_mut());
+        unsafe { lsm_close(db).ok() }
+    }
+}
+
+impl Drop for Tree {
+    fn drop(&mut self) {
+        if self.db.is_null() == false {
+            self.reset();
+            unsafe {
+                let _ = lsm_close(self.db);
+            }
+        }
     }
 }
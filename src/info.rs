@@ -0,0 +1,381 @@
+use crate::{Map, Tree};
+
+use lsm_ext::*;
+
+use std::ptr::null_mut;
+
+/// The layout of a database file, as reported by [`Map::structure`].
+///
+/// Its `Display` renders the levels, segments and log regions as a tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DbStructure {
+    /// The levels of the database, from the newest to the oldest.
+    pub levels: Vec<Level>,
+    /// The byte ranges of the log file in use; the last one is the region being written to.
+    pub log: [std::ops::Range<u64>; 3],
+}
+
+/// A level of the database, as listed by `Info::DbStructure`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Level {
+    /// The age of the level: `0` for segments flushed from the in-memory tree, and higher for those
+    /// merged from older levels.
+    pub age: u64,
+    /// The segments of the level, the newest first.
+    pub segments: Vec<Segment>,
+}
+
+/// A sorted run of pages within a level.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Segment {
+    /// The first page of the segment, or `0` if it has none, as is the case for a merge whose
+    /// output is empty so far.
+    pub first: u64,
+    /// The last page of the segment.
+    pub last: u64,
+    /// The root page of the segment’s b-tree, or `0` if it has none.
+    pub root: u64,
+    /// The number of pages in the segment.
+    pub size: u64,
+    /// The ranges of pages the segment is stored in, in order; only filled in by [`Map::structure`],
+    /// and empty if the segment has no pages.
+    pub pages: Vec<std::ops::RangeInclusive<u64>>,
+}
+
+impl std::fmt::Display for DbStructure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn branch(last: bool) -> (&'static str, &'static str) {
+            match last {
+                false => ("├── ", "│   "),
+                true => ("└── ", "    "),
+            }
+        }
+
+        writeln!(f, "levels")?;
+        for (n, level) in self.levels.iter().enumerate() {
+            let (head, indent) = branch(n + 1 == self.levels.len());
+            writeln!(f, "{head}level {n} (age {})", level.age)?;
+
+            for (m, segment) in level.segments.iter().enumerate() {
+                let (head, inner) = branch(m + 1 == level.segments.len());
+                writeln!(
+                    f,
+                    "{indent}{head}segment {}..={} (root {}, {} pages)",
+                    segment.first, segment.last, segment.root, segment.size
+                )?;
+
+                for (k, pages) in segment.pages.iter().enumerate() {
+                    let (head, _) = branch(k + 1 == segment.pages.len());
+                    writeln!(f, "{indent}{inner}{head}pages {pages:?}")?;
+                }
+            }
+        }
+
+        writeln!(f, "log")?;
+        for (n, region) in self.log.iter().enumerate() {
+            let (head, _) = branch(n + 1 == self.log.len());
+            writeln!(f, "{head}region {n}: bytes {region:?}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Counters describing a database connection, as reported by [`Map::stats`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// The number of pages read from the database file by this connection.
+    pub pages_read: u32,
+    /// The number of pages written to the database file by this connection.
+    pub pages_written: u32,
+    /// The size of the old in-memory tree, in KB, which is waiting to be flushed.
+    pub old_tree_size: u32,
+    /// The size of the current in-memory tree, in KB.
+    pub tree_size: u32,
+    /// The number of KB written to the database file since the most recent checkpoint.
+    pub checkpoint_size: u32,
+    /// The number of entries in the free-list.
+    pub freelist_size: u32,
+    /// The id of the compression scheme the database was written with: `1` if it is uncompressed, and
+    /// `0` if nothing has been written yet.
+    pub compression_id: u32,
+}
+
+impl Map {
+    /// Reads the layout of the database: its levels and segments, the pages each segment is stored
+    /// in, and the regions of the log file.
+    pub fn structure(&self) -> Result<DbStructure, crate::Error> {
+        let mut levels = self.tree.db_structure()?;
+        for segment in levels.iter_mut().flat_map(|level| &mut level.segments) {
+            if segment.first != 0 {
+                segment.pages = self.tree.array_structure(segment.first)?;
+            }
+        }
+
+        Ok(DbStructure {
+            levels,
+            log: self.tree.log_structure()?,
+        })
+    }
+
+    /// Reads back the counters of this connection.
+    ///
+    /// They are all kept in memory by LSM, so this is cheap enough to poll.
+    pub fn stats(&self) -> Result<Stats, crate::Error> {
+        let tree = &self.tree;
+        let count = |info| tree.info_i32(info).map(|n| n.max(0) as u32);
+        let (old, new) = tree.tree_size()?;
+
+        let mut compression_id = 0u32;
+        unsafe {
+            lsm_info(
+                tree.db,
+                Info::CompressionId,
+                &mut compression_id as *mut u32,
+            )
+            .ok()?
+        };
+
+        Ok(Stats {
+            pages_read: count(Info::NumRead)?,
+            pages_written: count(Info::NumWrite)?,
+            old_tree_size: old.max(0) as u32,
+            tree_size: new.max(0) as u32,
+            checkpoint_size: count(Info::CheckpointSize)?,
+            freelist_size: count(Info::FreelistSize)?,
+            compression_id,
+        })
+    }
+}
+
+impl Tree {
+    /// Queries one of the `lsm_info()` values reported as a string.
+    pub fn info_string(&self, info: Info) -> Result<String, Error> {
+        let mut ptr: *mut u8 = null_mut();
+        unsafe {
+            lsm_info(self.db, info, &mut ptr as *mut *mut u8).ok()?;
+            Ok(take_string(self.db, ptr))
+        }
+    }
+
+    /// Queries one of the `lsm_info()` values reported as a single integer.
+    pub fn info_i32(&self, info: Info) -> Result<i32, Error> {
+        let mut value = 0;
+        unsafe { lsm_info(self.db, info, &mut value as *mut i32).ok()? };
+
+        Ok(value)
+    }
+
+    /// The sizes of the old and current in-memory trees, in KB.
+    pub fn tree_size(&self) -> Result<(i32, i32), Error> {
+        let (mut old, mut new) = (0, 0);
+        unsafe {
+            lsm_info(
+                self.db,
+                Info::TreeSize,
+                &mut old as *mut i32,
+                &mut new as *mut i32,
+            )
+            .ok()?
+        };
+
+        Ok((old, new))
+    }
+
+    /// The levels of the database, from the newest to the oldest, without the pages of their segments.
+    pub fn db_structure(&self) -> Result<Vec<Level>, Error> {
+        let structure = self.info_string(Info::DbStructure)?;
+        parse_levels(&structure).ok_or(Error::Corrupt)
+    }
+
+    /// The ranges of pages of the segment starting at page `first`.
+    pub fn array_structure(&self, first: u64) -> Result<Vec<std::ops::RangeInclusive<u64>>, Error> {
+        let mut ptr: *mut u8 = null_mut();
+        let structure = unsafe {
+            lsm_info(
+                self.db,
+                Info::ArrayStructure,
+                first as i64,
+                &mut ptr as *mut *mut u8,
+            )
+            .ok()?;
+            take_string(self.db, ptr)
+        };
+
+        let numbers = parse_numbers(&structure).ok_or(Error::Corrupt)?;
+        match numbers.len() % 2 {
+            0 => Ok(numbers.chunks(2).map(|pair| pair[0]..=pair[1]).collect()),
+            _ => Err(Error::Corrupt),
+        }
+    }
+
+    /// The three regions of the log file, as byte ranges.
+    pub fn log_structure(&self) -> Result<[std::ops::Range<u64>; 3], Error> {
+        let structure = self.info_string(Info::LogStructure)?;
+        match parse_numbers(&structure).ok_or(Error::Corrupt)?[..] {
+            [a, b, c, d, e, f] => Ok([a..b, c..d, e..f]),
+            _ => Err(Error::Corrupt),
+        }
+    }
+
+    /// An estimate of the bytes taken by the database: its segments plus the in-memory trees.
+    pub fn approximate_bytes(&self) -> Result<u64, Error> {
+        let page_size = self.config(Config::PageSize, -1)? as u64;
+        let pages: u64 = self
+            .db_structure()?
+            .iter()
+            .flat_map(|level| &level.segments)
+            .map(|segment| segment.size)
+            .sum();
+
+        let (old, new) = self.tree_size()?;
+        Ok(pages * page_size + (old.max(0) + new.max(0)) as u64 * 1024)
+    }
+}
+
+impl Tree {
+    /// Estimates the number of entries by dividing the size of the database by the average size of
+    /// the first few entries; small maps are counted exactly.
+    pub fn approximate_len(&self) -> Result<usize, crate::Error> {
+        const SAMPLE: usize = 64;
+
+        let mut cursor = crate::RangeCursor::new_in(self, ..)?;
+        let (mut count, mut bytes) = (0, 0);
+        while count < SAMPLE {
+            match cursor.try_next()? {
+                Some((key, value)) => {
+                    count += 1;
+                    bytes += key.len() + value.len();
+                }
+                None => return Ok(count),
+            }
+        }
+
+        let average = (bytes / count).max(1) as u64;
+        Ok((self.approximate_bytes()? / average).max(count as u64) as usize)
+    }
+
+    /// Estimates the bytes taken by the keys in `range`, by placing its ends between the first and
+    /// last keys of the database and assuming that the keys in between are evenly spread.
+    pub fn approximate_size<'r, R>(&self, range: R) -> Result<u64, crate::Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        let mut keys = crate::Keys::new_in(self, ..)?;
+        let (Some(first), Some(last)) = (keys.try_next()?, keys.try_next_back()?) else {
+            // an empty map, or one with a single entry
+            let size = self.approximate_bytes()?;
+            return Ok(match self.range(range)?.try_is_empty()? {
+                true => 0,
+                false => size,
+            });
+        };
+
+        let position = |bound: std::ops::Bound<&&[u8]>, unbounded| match bound {
+            std::ops::Bound::Included(key) | std::ops::Bound::Excluded(key) => {
+                interpolate(&first, &last, key)
+            }
+            std::ops::Bound::Unbounded => unbounded,
+        };
+
+        let start = position(range.start_bound(), 0.0);
+        let end = position(range.end_bound(), 1.0);
+
+        let size = self.approximate_bytes()? as f64;
+        Ok((size * (end - start).max(0.0)) as u64)
+    }
+}
+
+/// Where `key` falls between `first` and `last`, from 0 to 1, going by the eight bytes that follow
+/// their common prefix.
+fn interpolate(first: &[u8], last: &[u8], key: &[u8]) -> f64 {
+    if key <= first {
+        return 0.0;
+    }
+    if key >= last {
+        return 1.0;
+    }
+
+    // any key between the two shares their common prefix
+    let common = first
+        .iter()
+        .zip(last)
+        .take_while(|(lhs, rhs)| lhs == rhs)
+        .count();
+    let value = |key: &[u8]| {
+        let mut bytes = [0; 8];
+        for (byte, &value) in bytes.iter_mut().zip(&key[common..]) {
+            *byte = value;
+        }
+        u64::from_be_bytes(bytes) as f64
+    };
+
+    let (lo, hi) = (value(first), value(last));
+    match hi > lo {
+        true => ((value(key) - lo) / (hi - lo)).clamp(0.0, 1.0),
+        false => 0.5, // the keys only differ past the bytes compared
+    }
+}
+
+/// Copies a string allocated by LSM and frees it.
+pub(crate) unsafe fn take_string(db: *mut lsm_db, ptr: *mut u8) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    let string = std::ffi::CStr::from_ptr(ptr as *const std::ffi::c_char);
+    let string = string.to_string_lossy().into_owned();
+
+    lsm_free(lsm_get_env(db), ptr);
+    string
+}
+
+/// Parses a list of `{age {first last root size} ...}` levels.
+fn parse_levels(structure: &str) -> Option<Vec<Level>> {
+    fn number(tokens: &mut std::str::SplitWhitespace<'_>) -> Option<u64> {
+        tokens.next()?.parse().ok()
+    }
+
+    let spaced = structure.replace('{', " { ").replace('}', " } ");
+    let mut tokens = spaced.split_whitespace();
+
+    let mut levels = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token != "{" {
+            return None;
+        }
+
+        let mut level = Level {
+            age: number(&mut tokens)?,
+            segments: Vec::new(),
+        };
+
+        loop {
+            match tokens.next()? {
+                "}" => break,
+                "{" => {
+                    let segment = Segment {
+                        first: number(&mut tokens)?,
+                        last: number(&mut tokens)?,
+                        root: number(&mut tokens)?,
+                        size: number(&mut tokens)?,
+                        pages: Vec::new(),
+                    };
+
+                    (tokens.next()? == "}").then_some(())?;
+                    level.segments.push(segment);
+                }
+                _ => return None,
+            }
+        }
+
+        levels.push(level);
+    }
+
+    Some(levels)
+}
+
+/// Parses a list of numbers separated by whitespace.
+fn parse_numbers(list: &str) -> Option<Vec<u64>> {
+    list.split_whitespace().map(|n| n.parse().ok()).collect()
+}
@@ -1,35 +1,196 @@
-#![allow(non_camel_case_types, dead_code, clippy::bool_comparison)]
+#![allow(
+    non_camel_case_types,
+    dead_code,
+    clippy::bool_comparison,
+    clippy::type_complexity
+)]
 #![doc = include_str!("../README.md")]
 
 extern crate lsm_ext;
 use lsm_ext::*;
 
-mod entry;
+mod batch;
+mod cursor;
+#[cfg(feature = "debug-introspection")]
+mod debug;
+mod drain;
+pub mod entry;
 mod file;
-mod map;
-mod range;
+mod info;
+pub mod map;
+mod options;
+mod page;
+pub mod range;
+mod snapshot;
+mod transaction;
+mod value;
+mod worker;
+
+pub use batch::WriteBatch;
+pub use cursor::{Cursor, CursorMut};
+#[cfg(feature = "debug-introspection")]
+pub use debug::{Block, Introspection};
+pub use drain::{Drain, ExtractIf};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use info::{DbStructure, Level, Segment, Stats};
+pub use lsm_ext::{Safety, Seek};
+pub use map::{IntoIter, IntoKeys, IntoValues, Iter, Map, ReadOnlyMap, Values};
+pub use options::{OpenOptions, Settings};
+pub use page::{Page, Token};
+pub use range::{Keys, RangeBounds, RangeCursor};
+pub use snapshot::Snapshot;
+pub use transaction::{Savepoint, Transaction};
+pub use value::ValueRef;
+pub use worker::{Worker, WorkerOptions};
 
 #[cfg(test)]
 mod test;
 
 pub(crate) struct Tree {
     db: *mut lsm_db,
+    path: String,
+    depth: std::cell::Cell<u32>, // the number of nested transactions that are open
+    pins: std::cell::Cell<u32>,  // the number of snapshots that are open
+    cache: std::cell::Cell<*mut lsm_cursor>, // a cursor kept open between uses; null if there is none
 }
 
 impl Tree {
-    pub fn entry<'e>(&self, key: &'e [u8]) -> entry::Entry<'e> {
-        entry::Entry::new_in(self.db, key)
+    /// Opens a cursor, reusing the cached one if there is one; it is positioned nowhere in particular.
+    pub fn acquire(&self) -> Result<*mut lsm_cursor, lsm_ext::Error> {
+        let cursor = self.cache.replace(std::ptr::null_mut());
+        if cursor.is_null() == false {
+            return Ok(cursor);
+        }
+
+        let mut cursor = std::ptr::null_mut();
+        unsafe { lsm_csr_open(self.db, &mut cursor).ok()? };
+        Ok(cursor)
+    }
+
+    /// Hands a cursor back, closing whichever one is not kept.
+    ///
+    /// A cursor is only kept for reuse while a transaction or a [`Snapshot`] is open, as either pins
+    /// the snapshot anyway: otherwise, a cursor left open would keep the map reading from the
+    /// snapshot it was opened on, missing whatever other connections commit, and keep them from
+    /// reusing the space it holds.
+    pub fn release(&self, cursor: *mut lsm_cursor) {
+        let cursor = match self.depth.get() + self.pins.get() {
+            0 => cursor,
+            _ => self.cache.replace(cursor),
+        };
+        unsafe {
+            let _ = lsm_csr_close(cursor); // ignores null ptrs properly
+        }
+    }
+
+    /// Closes the cached cursor.
+    ///
+    /// An open cursor keeps the connection reading from the snapshot it was opened on, and LSM only
+    /// lets a connection write, or close, once it has let go of it; so this precedes every write.
+    pub fn reset(&self) {
+        let cursor = self.cache.replace(std::ptr::null_mut());
+        unsafe {
+            let _ = lsm_csr_close(cursor); // ignores null ptrs properly
+        }
+    }
+
+    pub fn entry<'e>(&'e self, key: &'e [u8]) -> Result<entry::Entry<'e>, Error> {
+        entry::Entry::new_in(self, key)
     }
 
-    pub fn range<'r, R>(&self, range: R) -> range::RangeBounds<'r>
+    pub fn range<'r, R>(&self, range: R) -> Result<range::RangeBounds<'_>, Error>
     where
         R: std::ops::RangeBounds<&'r [u8]>,
     {
-        range::RangeBounds::new_in(self.db, range).unwrap()
+        Ok(range::RangeBounds::new_in(self, range)?)
+    }
+
+    /// The entry that `bound` lands on when seeking in `direction`, copied out.
+    pub fn nearest(
+        &self,
+        bound: std::ops::Bound<&[u8]>,
+        direction: range::Direction,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        let mut bound = range::Bound::new_in(self, bound, direction)?;
+        match range::found(bound.cursor())? {
+            Some(_) => Ok(Some((bound.key()?.to_vec(), bound.val()?.to_vec()))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn insert(&self, key: &[u8], value: &[u8]) -> Result<(), lsm_ext::Error> {
+        let (key_ptr, key_len) = (key.as_ptr(), key.len() as u32);
+        let (val_ptr, val_len) = (value.as_ptr(), value.len() as u32);
+
+        self.reset();
+        unsafe { lsm_insert(self.db, key_ptr, key_len, val_ptr, val_len).ok() }
+    }
+
+    pub fn remove(&self, key: &[u8]) -> Result<(), lsm_ext::Error> {
+        self.reset();
+        unsafe { lsm_delete(self.db, key.as_ptr(), key.len() as u32).ok() }
+    }
+
+    /// Removes every key within `range`; callers wanting it to be atomic wrap it in a transaction.
+    pub fn remove_range<'r, R>(&self, range: R) -> Result<(), lsm_ext::Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        use std::ops::Bound::*;
+
+        // `lsm_delete_range()` excludes both of its end points, so inclusive ends are removed on
+        // their own, and unbounded ones are replaced by the first possible and last existing keys
+        let last;
+        let (end, end_included) = match range.end_bound() {
+            Included(key) => (*key, true),
+            Excluded(key) => (*key, false),
+            Unbounded => {
+                let mut bound = range::Bound::new_in(self, Unbounded, range::Direction::Prev)?;
+                match bound.cursor() {
+                    Err(lsm_ext::Error::NoEnt) => return Ok(()), // the map is empty
+                    result => result?,
+                };
+
+                last = bound.key()?.to_vec();
+                (last.as_slice(), true)
+            }
+        };
+
+        let (start, start_included) = match range.start_bound() {
+            Included(key) => (*key, true),
+            Excluded(key) => (*key, false),
+            Unbounded => (&[][..], true), // the empty key sorts before every other
+        };
+
+        match start.cmp(end) {
+            std::cmp::Ordering::Greater => return Ok(()),
+            std::cmp::Ordering::Equal => {
+                return match start_included && end_included {
+                    true => self.remove(start),
+                    false => Ok(()),
+                };
+            }
+            std::cmp::Ordering::Less => {}
+        }
+
+        let (start_ptr, start_len) = (start.as_ptr(), start.len() as u32);
+        let (end_ptr, end_len) = (end.as_ptr(), end.len() as u32);
+
+        self.reset();
+        unsafe { lsm_delete_range(self.db, start_ptr, start_len, end_ptr, end_len).ok()? };
+
+        if start_included {
+            self.remove(start)?;
+        }
+        if end_included {
+            self.remove(end)?;
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Error {
     Error,
     Busy,
@@ -43,6 +204,25 @@ pub enum Error {
     NoEnt,
 }
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Error::Error => "generic error",
+            Error::Busy => "database is busy",
+            Error::Nomem => "out of memory",
+            Error::IoErr => "disk I/O error",
+            Error::Corrupt => "database disk image is malformed",
+            Error::Full => "database or disk is full",
+            Error::CantOpen => "unable to open database file",
+            Error::Protocol => "database locking protocol error",
+            Error::Misuse => "library used incorrectly",
+            Error::NoEnt => "no such file or directory",
+        })
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl From<lsm_ext::Error> for Error {
     /// SAFETY: panics if `raw` is `Error::Ok`
     fn from(raw: lsm_ext::Error) -> Self {
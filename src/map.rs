@@ -1,89 +1,352 @@
-use crate::{entry::*, range::*, Error, Tree};
+use crate::{
+    drain::BATCH, entry::*, range::*, Cursor, CursorMut, Error, OpenOptions, Settings, Transaction,
+    Tree, ValueRef, Worker,
+};
+use lsm_ext::Seek;
 
-use std::cell::RefCell;
-use std::collections::BTreeMap;
-use std::marker::PhantomData;
+use std::collections::{BTreeMap, VecDeque};
 
-pub struct Map<'a> {
-    tree: Tree,
-    marker: PhantomData<&'a [u8]>,
+/// An ordered map backed by an LSM database file.
+///
+/// The API mirrors [`BTreeMap`] over byte slices. Methods that have to touch the database panic if
+/// the underlying storage reports an error; each of them has a `try_` counterpart that returns the
+/// [`Error`] instead.
+///
+/// Every read opens a cursor of its own, so it sees whatever other connections to the file have
+/// committed. Reads made through a [`Snapshot`], or within a transaction, reuse a cursor that is kept
+/// open between calls instead, which makes point lookups considerably cheaper.
+///
+/// Lookups borrow the map immutably and writes borrow it mutably, so any reference into the
+/// database’s buffers (a [`ValueRef`], or an entry lent out by a [`RangeCursor`]) is guaranteed to be
+/// gone before the map is modified.
+pub struct Map {
+    pub(crate) worker: Option<Worker>, // declared first, so it is stopped before the map is closed
+    pub(crate) tree: Tree,
 }
 
-impl<'a> Map<'a> {
+impl Map {
     pub fn new(path: &str) -> Result<Self, Error> {
         Ok(Map {
+            worker: None,
             tree: Tree::new(path)?,
-            marker: Default::default(),
         })
     }
 
+    /// Creates a blank set of options for configuring how a database is opened.
+    pub fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+
+    /// Opens the database at `path` without write access.
+    ///
+    /// See [`ReadOnlyMap`] for details.
+    pub fn open_read_only(path: &str) -> Result<ReadOnlyMap, Error> {
+        Map::options().open_read_only(path)
+    }
+
+    /// Reads back the configuration in effect for this database.
+    pub fn settings(&self) -> Result<Settings, Error> {
+        Settings::new_in(&self.tree)
+    }
+
+    /// Closes the database, returning any error encountered while checkpointing or releasing the file.
+    ///
+    /// Dropping a `Map` closes it as well, but silently discards such errors.
+    ///
+    /// A background worker is stopped first; if it stopped early because of an error, that error is
+    /// returned once the database is closed.
+    pub fn close(mut self) -> Result<(), Error> {
+        let stopped = self.stop_worker();
+        self.tree.close()?;
+        stopped
+    }
+
+    /// Writes the in-memory tree out to the database file as a new segment.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.tree.flush()?)
+    }
+
+    /// Syncs the database file and writes a checkpoint, returning the number of bytes written to the
+    /// file since the previous one.
+    pub fn checkpoint(&mut self) -> Result<u64, Error> {
+        Ok(self.tree.checkpoint()? as u64 * 1024)
+    }
+
+    /// Merges up to `kilobytes` KB of segments, `segments` or more at a time, returning the number
+    /// of KB written.
+    ///
+    /// Fails with [`Error::Misuse`] if `segments` is zero.
+    pub fn work(&mut self, segments: u32, kilobytes: u32) -> Result<u32, Error> {
+        Ok(self.tree.work(segments, kilobytes)?)
+    }
+
+    /// Flushes the in-memory tree and merges the database until it is a single segment, then
+    /// checkpoints it, leaving a file that can be shipped as is.
+    ///
+    /// Fails with [`Error::Busy`] if merging stops making progress before then, as it does while
+    /// other connections hold on to older snapshots; whatever was merged is checkpointed regardless.
+    pub fn compact_fully(&mut self) -> Result<(), Error> {
+        const BUDGET: u32 = 16 * 1024; // KB merged per call, so that other connections get a turn
+
+        self.tree.flush()?;
+        let merged = loop {
+            let segments: usize = self
+                .tree
+                .db_structure()?
+                .iter()
+                .map(|level| level.segments.len())
+                .sum();
+            if segments <= 1 {
+                break true;
+            }
+
+            // merging a single segment at a time lets `lsm_work()` fold every level into one
+            if self.tree.work(1, BUDGET)? == 0 {
+                break false;
+            }
+        };
+
+        self.tree.checkpoint()?;
+        match merged {
+            true => Ok(()),
+            false => Err(Error::Busy),
+        }
+    }
+
+    /// Starts a transaction; it has to be committed explicitly and is rolled back when dropped.
+    ///
+    /// Starting a transaction while one is already open nests the new one inside of it.
+    pub fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        Transaction::begin(self)
+    }
+
+    /// Runs `f` inside of a transaction that is committed if it returns `Ok` and rolled back otherwise.
+    pub fn transact<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Transaction<'_>) -> Result<T, E>,
+        E: From<Error>,
+    {
+        let mut transaction = self.transaction()?;
+        let result = f(&mut transaction)?;
+        transaction.commit()?;
+
+        Ok(result)
+    }
+
     #[inline]
     /// Returns a reference to the value corresponding to the key.
-    pub fn get(&self, key: &'a [u8]) -> Option<&'a [u8]> {
-        match self.tree.entry(key) {
-            Entry::Vacant(_) => None,
-            Entry::Occupied(entry) => Some(entry.get()),
+    pub fn get(&self, key: &[u8]) -> Option<ValueRef<'_>> {
+        self.try_get(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`get`](Map::get).
+    pub fn try_get(&self, key: &[u8]) -> Result<Option<ValueRef<'_>>, Error> {
+        match self.lookup(key)? {
+            Some(bound) => Ok(Some(ValueRef::new_in(bound)?)),
+            None => Ok(None),
         }
     }
 
     #[inline]
     /// Returns the key-value pair corresponding to the supplied key.
-    pub fn get_key_value(&self, key: &'a [u8]) -> Option<(&'a [u8], &'a [u8])> {
-        match self.tree.entry(key) {
-            Entry::Vacant(_) => None,
-            Entry::Occupied(entry) => Some((entry.key(), entry.get())),
+    pub fn get_key_value(&self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_get_key_value(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`get_key_value`](Map::get_key_value).
+    pub fn try_get_key_value(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        match self.lookup(key)? {
+            Some(bound) => Ok(Some((bound.key()?.into(), bound.val()?.into()))),
+            None => Ok(None),
         }
     }
 
+    #[inline]
+    /// Returns copies of the values corresponding to each of `keys`, in the same order.
+    ///
+    /// The keys are looked up in sorted order with a single cursor, which is quicker than calling
+    /// [`get`](Map::get) for each of them.
+    pub fn get_many(&self, keys: &[&[u8]]) -> Vec<Option<Vec<u8>>> {
+        self.try_get_many(keys).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`get_many`](Map::get_many).
+    pub fn try_get_many(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>, Error> {
+        let mut order: Vec<_> = (0..keys.len()).collect();
+        order.sort_unstable_by_key(|&n| keys[n]);
+
+        let mut values = vec![None; keys.len()];
+        let mut bound = Bound::open_in(&self.tree)?;
+        for n in order {
+            if bound.seek(keys[n], Seek::EQ)? {
+                values[n] = Some(bound.val()?.to_vec());
+            }
+        }
+
+        Ok(values)
+    }
+
     #[inline(always)]
     /// Returns the first key-value pair in the map. The key in this pair is the minimum key in the map.
-    pub fn first_key_value(&self) -> Option<(&'a [u8], &'a [u8])> {
-        self.iter().next()
+    pub fn first_key_value(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_first_key_value().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`first_key_value`](Map::first_key_value).
+    pub fn try_first_key_value(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.try_iter()?.range.try_next()
     }
 
     #[inline(always)]
     /// Returns the first entry in the map for in-place manipulation. The key of this entry is the minimum key in the map.
-    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'a>> {
-        let mut bound =
-            Bound::new_in(self.tree.db, std::ops::Bound::Unbounded, Direction::Next).ok()?;
-        bound.cursor().ok()?; // ensure we are not empty
-        Some(OccupiedEntry(RefCell::new(bound)))
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_>> {
+        self.try_first_entry().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`first_entry`](Map::first_entry).
+    pub fn try_first_entry(&mut self) -> Result<Option<OccupiedEntry<'_>>, Error> {
+        let bound = Bound::new_in(&self.tree, std::ops::Bound::Unbounded, Direction::Next)?;
+        occupied(bound)
     }
 
     #[inline(always)]
     /// Removes and returns the first element in the map. The key of this element is the minimum key that was in the map.
-    pub fn pop_first(&mut self) -> Option<(impl AsRef<[u8]>, impl AsRef<[u8]>)> {
-        Some(self.first_entry()?.remove_entry())
+    pub fn pop_first(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_pop_first().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`pop_first`](Map::pop_first).
+    pub fn try_pop_first(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.try_first_entry()?
+            .map(OccupiedEntry::try_remove_entry)
+            .transpose()
     }
 
     #[inline(always)]
     /// Returns the last key-value pair in the map. The key in this pair is the maximum key in the map.
-    pub fn last_key_value(&self) -> Option<(&'a [u8], &'a [u8])> {
-        self.iter().next_back()
+    pub fn last_key_value(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_last_key_value().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`last_key_value`](Map::last_key_value).
+    pub fn try_last_key_value(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.try_iter()?.range.try_next_back()
     }
 
     #[inline(always)]
     /// Returns the last entry in the map for in-place manipulation. The key of this entry is the maximum key in the map.
-    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'a>> {
-        let mut bound =
-            Bound::new_in(self.tree.db, std::ops::Bound::Unbounded, Direction::Prev).ok()?;
-        bound.cursor().ok()?; // ensure we are not empty
-        Some(OccupiedEntry(RefCell::new(bound)))
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_>> {
+        self.try_last_entry().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`last_entry`](Map::last_entry).
+    pub fn try_last_entry(&mut self) -> Result<Option<OccupiedEntry<'_>>, Error> {
+        let bound = Bound::new_in(&self.tree, std::ops::Bound::Unbounded, Direction::Prev)?;
+        occupied(bound)
     }
 
     #[inline(always)]
     /// Removes and returns the last element in the map. The key of this element is the maximum key that was in the map.
-    pub fn pop_last(&mut self) -> Option<(impl AsRef<[u8]>, impl AsRef<[u8]>)> {
-        Some(self.last_entry()?.remove_entry())
+    pub fn pop_last(&mut self) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_pop_last().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`pop_last`](Map::pop_last).
+    pub fn try_pop_last(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.try_last_entry()?
+            .map(OccupiedEntry::try_remove_entry)
+            .transpose()
+    }
+
+    #[inline]
+    /// Returns the key-value pair with the greatest key less than or equal to `key`.
+    pub fn floor(&self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_floor(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`floor`](Map::floor).
+    pub fn try_floor(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.tree
+            .nearest(std::ops::Bound::Included(key), Direction::Prev)
+    }
+
+    #[inline]
+    /// Returns the greatest key less than or equal to `key`.
+    ///
+    /// Cheaper than [`floor`](Map::floor), as the value is not copied out.
+    pub fn floor_key(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.try_floor_key(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`floor_key`](Map::floor_key).
+    pub fn try_floor_key(&self, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let mut bound = Bound::seek_in(&self.tree, key, Seek::LE)?;
+        match found(bound.cursor())? {
+            Some(_) => Ok(Some(bound.key()?.to_vec())),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    /// Returns the key-value pair with the least key greater than or equal to `key`.
+    pub fn ceiling(&self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_ceiling(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`ceiling`](Map::ceiling).
+    pub fn try_ceiling(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.tree
+            .nearest(std::ops::Bound::Included(key), Direction::Next)
+    }
+
+    #[inline]
+    /// Returns the key-value pair with the greatest key strictly less than `key`.
+    pub fn predecessor(&self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_predecessor(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`predecessor`](Map::predecessor).
+    pub fn try_predecessor(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.tree
+            .nearest(std::ops::Bound::Excluded(key), Direction::Prev)
+    }
+
+    #[inline]
+    /// Returns the key-value pair with the least key strictly greater than `key`.
+    pub fn successor(&self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_successor(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`successor`](Map::successor).
+    pub fn try_successor(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.tree
+            .nearest(std::ops::Bound::Excluded(key), Direction::Next)
     }
 
     #[inline(always)]
     /// Returns `true` if the map contains a value for the specified key.
-    pub fn contains_key(&self, key: &'a [u8]) -> bool {
-        match self.tree.entry(key) {
-            Entry::Vacant(_) => false,
-            Entry::Occupied(_) => true,
-        }
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.try_contains_key(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`contains_key`](Map::contains_key).
+    pub fn try_contains_key(&self, key: &[u8]) -> Result<bool, Error> {
+        Ok(self.lookup(key)?.is_some())
     }
 
     #[inline]
@@ -92,56 +355,150 @@ impl<'a> Map<'a> {
     /// If the map did not have this key present, `None` is returned.
     ///
     /// If the map did have this key present, the value is updated, and the old value is returned.
-    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<impl AsRef<[u8]>> {
-        match self.tree.entry(key) {
-            Entry::Vacant(entry) => {
-                entry.insert(value);
-                None
-            }
-            Entry::Occupied(entry) => Some(entry.insert(value)),
-        }
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        self.try_insert(key, value).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`insert`](Map::insert).
+    pub fn try_insert(&mut self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        let old = match self.lookup(key)? {
+            Some(bound) => Some(bound.val()?.to_vec()),
+            None => None,
+        };
+
+        self.tree.insert(key, value)?;
+        Ok(old)
     }
 
     #[inline]
     /// Removes a key from the map, returning the value at the key if the key was previously in the map.
-    pub fn remove(&mut self, key: &[u8]) -> Option<impl AsRef<[u8]>> {
-        match self.tree.entry(key) {
-            Entry::Vacant(_) => None,
-            Entry::Occupied(entry) => Some(entry.remove()),
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.try_remove(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`remove`](Map::remove).
+    pub fn try_remove(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match self.lookup(key)? {
+            Some(bound) => Ok(Some(OccupiedEntry(bound).try_remove()?)),
+            None => Ok(None),
         }
     }
 
     #[inline]
     /// Removes a key from the map, returning the stored key and value if the key was previously in the map.
-    pub fn remove_entry(&mut self, key: &[u8]) -> Option<(impl AsRef<[u8]>, impl AsRef<[u8]>)> {
-        match self.tree.entry(key) {
-            Entry::Vacant(_) => None,
-            Entry::Occupied(entry) => Some(entry.remove_entry()),
+    pub fn remove_entry(&mut self, key: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.try_remove_entry(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`remove_entry`](Map::remove_entry).
+    pub fn try_remove_entry(&mut self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        match self.lookup(key)? {
+            Some(bound) => Ok(Some(OccupiedEntry(bound).try_remove_entry()?)),
+            None => Ok(None),
         }
     }
 
+    #[inline]
+    /// Constructs a double-ended iterator over the elements whose keys start with `prefix`.
+    pub fn prefix(&self, prefix: &[u8]) -> RangeBounds<'_> {
+        self.try_prefix(prefix).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`prefix`](Map::prefix).
+    pub fn try_prefix(&self, prefix: &[u8]) -> Result<RangeBounds<'_>, Error> {
+        let end = prefix_end(prefix);
+        self.tree.range((
+            std::ops::Bound::Included(prefix),
+            end.as_deref()
+                .map_or(std::ops::Bound::Unbounded, std::ops::Bound::Excluded),
+        ))
+    }
+
+    #[inline]
+    /// Removes every element within `range`.
+    ///
+    /// The removal is a single range tombstone, no matter how many elements the range holds.
+    pub fn remove_range<'r, R: std::ops::RangeBounds<&'r [u8]>>(&mut self, range: R) {
+        self.try_remove_range(range).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`remove_range`](Map::remove_range).
+    pub fn try_remove_range<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &mut self,
+        range: R,
+    ) -> Result<(), Error> {
+        self.transact(|tx| Ok(tx.tree.remove_range(range)?))
+    }
+
+    #[inline]
+    /// Removes every element whose key starts with `prefix`.
+    pub fn remove_prefix(&mut self, prefix: &[u8]) {
+        self.try_remove_prefix(prefix).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`remove_prefix`](Map::remove_prefix).
+    pub fn try_remove_prefix(&mut self, prefix: &[u8]) -> Result<(), Error> {
+        let end = prefix_end(prefix);
+        self.try_remove_range((
+            std::ops::Bound::Included(prefix),
+            end.as_deref()
+                .map_or(std::ops::Bound::Unbounded, std::ops::Bound::Excluded),
+        ))
+    }
+
+    #[inline]
+    /// Clears the map, removing all elements.
+    pub fn clear(&mut self) {
+        self.try_clear().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`clear`](Map::clear).
+    pub fn try_clear(&mut self) -> Result<(), Error> {
+        self.try_remove_range(..)
+    }
+
     #[inline]
     /// Retains only the elements specified by the predicate.
     ///
     /// In other words, remove all pairs (k, v) for which f(&k, &mut v) returns false. The elements are visited in ascending key order.
-    pub fn retain<F>(&mut self, mut pred: F)
+    ///
+    /// The changes are made within a single transaction.
+    pub fn retain<F>(&mut self, pred: F)
+    where
+        F: FnMut(&mut std::borrow::Cow<'_, [u8]>) -> bool,
+    {
+        self.try_retain(pred).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`retain`](Map::retain).
+    pub fn try_retain<F>(&mut self, mut pred: F) -> Result<(), Error>
     where
         F: FnMut(&mut std::borrow::Cow<'_, [u8]>) -> bool,
     {
-        for (key, val) in self.iter() {
-            let mut value = std::borrow::Cow::Borrowed(val);
+        self.scan(.., |tree, key, val| {
+            let mut value = std::borrow::Cow::Borrowed(val.as_slice());
             let keep = pred(&mut value);
 
             match (keep, value) {
                 (true, std::borrow::Cow::Borrowed(_)) => {}
                 (true, std::borrow::Cow::Owned(changes)) => {
-                    self.insert(key, changes.as_ref());
+                    tree.insert(&key, &changes)?;
                 }
                 (false, _) => {
-                    self.remove(key);
+                    tree.remove(&key)?;
                 }
             }
-        }
+
+            Ok(())
+        })
     }
 
     #[inline]
@@ -153,82 +510,489 @@ impl<'a> Map<'a> {
         K: AsRef<[u8]> + Ord,
         V: AsRef<[u8]>,
     {
-        other.retain(|key, val| {
-            self.insert(key.as_ref(), val.as_ref());
-            false
-        })
+        self.try_append(other).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`append`](Map::append).
+    ///
+    /// On error, the elements that have not been moved yet are left in `other`.
+    pub fn try_append<K, V>(&mut self, other: &mut BTreeMap<K, V>) -> Result<(), Error>
+    where
+        K: AsRef<[u8]> + Ord,
+        V: AsRef<[u8]>,
+    {
+        while let Some((key, val)) = other.pop_first() {
+            if let Err(error) = self.tree.insert(key.as_ref(), val.as_ref()) {
+                other.insert(key, val);
+                return Err(error.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Moves all elements from another map into self, leaving other empty.
+    ///
+    /// The elements are streamed from `other` a batch at a time, each batch being removed from `other`
+    /// within the transaction it was read in, once it has been written to `self`: whatever other
+    /// connections write to `other` meanwhile is either moved or left in place, never lost, and a
+    /// failure part of the way through leaves at most one batch in both maps, but nothing in neither.
+    pub fn append_map(&mut self, other: &mut Map) {
+        self.try_append_map(other).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`append_map`](Map::append_map).
+    pub fn try_append_map(&mut self, other: &mut Map) -> Result<(), Error> {
+        other.drain_batches(.., |batch| self.try_extend(batch))
+    }
+
+    /// Fallible version of [`extend`](Extend::extend).
+    ///
+    /// The elements are written in transactions of a bounded size, so that importing any number of
+    /// them never holds more than a few in memory; each transaction writes its elements in key order,
+    /// which costs nothing when the input is sorted already. On error, the transactions that were
+    /// committed stay in place.
+    pub fn try_extend<K, V, I>(&mut self, iter: I) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut iter = iter.into_iter();
+        let mut chunk = Vec::with_capacity(BATCH);
+
+        loop {
+            chunk.extend(iter.by_ref().take(BATCH));
+            if chunk.is_empty() {
+                return Ok(());
+            }
+
+            let sorted = chunk
+                .windows(2)
+                .all(|pair| pair[0].0.as_ref() < pair[1].0.as_ref());
+            if sorted == false {
+                // stable, so the last value given for a key is still the one written last
+                chunk.sort_by(|lhs, rhs| lhs.0.as_ref().cmp(rhs.0.as_ref()));
+            }
+
+            self.transact(|tx| {
+                for (key, val) in chunk.drain(..) {
+                    tx.tree.insert(key.as_ref(), val.as_ref())?;
+                }
+
+                Ok::<_, Error>(())
+            })?;
+        }
     }
 
     #[inline(always)]
     /// Constructs a double-ended iterator over a sub-range of elements in the map. The simplest way is to use the range syntax `min..max`, thus `range(min..max)` will yield elements from min (inclusive) to max (exclusive). The range may also be entered as `(Bound<T>, Bound<T>)`, so for example `range((Excluded(4), Included(10)))` will yield a left-exclusive, right-inclusive range from 4 to 10.
-    pub fn range<'r, R: std::ops::RangeBounds<&'r [u8]>>(&self, range: R) -> RangeBounds<'r> {
+    pub fn range<'r, R: std::ops::RangeBounds<&'r [u8]>>(&self, range: R) -> RangeBounds<'_> {
+        self.try_range(range).unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`range`](Map::range).
+    pub fn try_range<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &self,
+        range: R,
+    ) -> Result<RangeBounds<'_>, Error> {
         self.tree.range(range)
     }
 
+    #[inline(always)]
+    /// Constructs a cursor over a sub-range of elements in the map that lends out its keys and values without copying them.
+    pub fn range_cursor<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &self,
+        range: R,
+    ) -> RangeCursor<'_> {
+        self.try_range_cursor(range).unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`range_cursor`](Map::range_cursor).
+    pub fn try_range_cursor<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &self,
+        range: R,
+    ) -> Result<RangeCursor<'_>, Error> {
+        Ok(RangeCursor::new_in(&self.tree, range)?)
+    }
+
+    #[inline(always)]
+    /// Opens a [`Cursor`] that can be positioned anywhere in the map.
+    pub fn cursor(&self) -> Cursor<'_> {
+        self.try_cursor().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`cursor`](Map::cursor).
+    pub fn try_cursor(&self) -> Result<Cursor<'_>, Error> {
+        Ok(Cursor::new_in(&self.tree)?)
+    }
+
+    #[inline(always)]
+    /// Opens a [`CursorMut`] that can be positioned anywhere in the map and modify it in place.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_> {
+        self.try_cursor_mut().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`cursor_mut`](Map::cursor_mut).
+    pub fn try_cursor_mut(&mut self) -> Result<CursorMut<'_>, Error> {
+        Ok(CursorMut::new_in(&self.tree)?)
+    }
+
     #[inline(always)]
     /// Gets the given key’s corresponding entry in the map for in-place manipulation.
-    pub fn entry<'e>(&self, key: &'e [u8]) -> Entry<'e> {
+    pub fn entry<'e>(&'e mut self, key: &'e [u8]) -> Entry<'e> {
+        self.try_entry(key).unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`entry`](Map::entry).
+    pub fn try_entry<'e>(&'e mut self, key: &'e [u8]) -> Result<Entry<'e>, Error> {
         self.tree.entry(key)
     }
 
     #[inline]
     /// Splits the collection into two at the given key. Returns everything after the given key, including the key.
     pub fn split_off(&mut self, key: &[u8]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.try_split_off(key).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`split_off`](Map::split_off).
+    pub fn try_split_off(&mut self, key: &[u8]) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, Error> {
         let mut other = BTreeMap::new();
-        for (key, val) in self.range(key..) {
-            other.insert(key.into(), val.into());
-            self.remove(key);
+        let mut range = self.tree.range(key..)?;
+        while let Some((key, val)) = range.try_next()? {
+            other.insert(key, val);
         }
 
-        other
+        drop(range);
+        self.try_remove_range(key..)?;
+
+        Ok(other)
+    }
+
+    #[inline]
+    /// Moves everything after the given key, including the key, into `other`.
+    ///
+    /// Unlike [`split_off`](Map::split_off), the elements are streamed rather than held in memory,
+    /// so this can shard a database of any size. They are moved a batch at a time, each batch being
+    /// removed from `self` within the transaction it was read in, once it has been written to
+    /// `other`: whatever other connections write to `self` meanwhile is either moved or left in
+    /// place, never lost, and a failure part of the way through leaves at most one batch in both
+    /// maps, but nothing in neither. `other` must not be a connection to the same file.
+    pub fn split_off_into(&mut self, key: &[u8], other: &mut Map) {
+        self.try_split_off_into(key, other).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`split_off_into`](Map::split_off_into).
+    pub fn try_split_off_into(&mut self, key: &[u8], other: &mut Map) -> Result<(), Error> {
+        self.drain_batches(key.., |batch| other.write(batch.into_iter().collect()))
+    }
+
+    #[inline]
+    /// Moves everything after the given key, including the key, into a new database at `path`,
+    /// as [`split_off_into`](Map::split_off_into) does.
+    ///
+    /// Panics if a database with any entries exists at `path` already, rather than merging into it.
+    pub fn split_off_to_path(&mut self, key: &[u8], path: &str) -> Map {
+        self.try_split_off_to_path(key, path).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`split_off_to_path`](Map::split_off_to_path).
+    ///
+    /// Fails with [`Error::Misuse`] if a database with any entries exists at `path` already.
+    pub fn try_split_off_to_path(&mut self, key: &[u8], path: &str) -> Result<Map, Error> {
+        let mut other = Map::new(path)?;
+        if other.try_is_empty()? == false {
+            return Err(Error::Misuse);
+        }
+
+        self.try_split_off_into(key, &mut other)?;
+
+        Ok(other)
     }
 
     #[inline(always)]
     /// Creates a consuming iterator visiting all the keys, in sorted order. The map cannot be used after calling this.
-    pub fn into_keys(self) -> impl Iterator<Item = &'a [u8]> {
-        self.keys()
+    pub fn into_keys(self) -> IntoKeys {
+        self.try_into_keys().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`into_keys`](Map::into_keys).
+    pub fn try_into_keys(self) -> Result<IntoKeys, Error> {
+        Ok(IntoKeys(self.try_into_iter()?))
     }
 
     #[inline(always)]
     /// Creates a consuming iterator visiting all the values, in order by key. The map cannot be used after calling this.
-    pub fn into_values(self) -> impl Iterator<Item = &'a [u8]> {
-        self.values()
+    pub fn into_values(self) -> IntoValues {
+        self.try_into_values().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`into_values`](Map::into_values).
+    pub fn try_into_values(self) -> Result<IntoValues, Error> {
+        Ok(IntoValues(self.try_into_iter()?))
+    }
+
+    /// Fallible version of [`into_iter`](IntoIterator::into_iter).
+    pub fn try_into_iter(self) -> Result<IntoIter, Error> {
+        Ok(IntoIter::new(self))
     }
 
     #[inline(always)]
     /// Gets an iterator over the entries of the map, sorted by key.
-    pub fn iter(&self) -> Iter<'a> {
-        Iter {
-            range: self.tree.range(..),
-        }
+    pub fn iter(&self) -> Iter<'_> {
+        self.try_iter().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`iter`](Map::iter).
+    pub fn try_iter(&self) -> Result<Iter<'_>, Error> {
+        Ok(Iter {
+            range: self.tree.range(..)?,
+        })
     }
 
     #[inline(always)]
     /// Gets an iterator over the keys of the map, in sorted order.
-    pub fn keys(&self) -> impl Iterator<Item = &'a [u8]> {
-        self.iter().map(|(key, _)| key)
+    pub fn keys(&self) -> Keys<'_> {
+        self.try_keys().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`keys`](Map::keys).
+    pub fn try_keys(&self) -> Result<Keys<'_>, Error> {
+        self.try_range_keys(..)
+    }
+
+    #[inline(always)]
+    /// Gets an iterator over the keys within a sub-range of the map, in sorted order.
+    pub fn range_keys<'r, R: std::ops::RangeBounds<&'r [u8]>>(&self, range: R) -> Keys<'_> {
+        self.try_range_keys(range).unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`range_keys`](Map::range_keys).
+    pub fn try_range_keys<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &self,
+        range: R,
+    ) -> Result<Keys<'_>, Error> {
+        Ok(Keys::new_in(&self.tree, range)?)
+    }
+
+    #[inline]
+    /// Counts the keys within a sub-range of the map, without reading any values.
+    pub fn count_range<'r, R: std::ops::RangeBounds<&'r [u8]>>(&self, range: R) -> usize {
+        self.try_count_range(range).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`count_range`](Map::count_range).
+    pub fn try_count_range<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &self,
+        range: R,
+    ) -> Result<usize, Error> {
+        self.try_range_keys(range)?.try_count()
     }
 
     #[inline(always)]
     /// Gets an iterator over the values of the map, in order by key.
-    pub fn values(&self) -> impl Iterator<Item = &'a [u8]> {
-        self.iter().map(|(_, value)| value)
+    pub fn values(&self) -> Values<'_> {
+        self.try_values().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`values`](Map::values).
+    pub fn try_values(&self) -> Result<Values<'_>, Error> {
+        Ok(Values(self.try_iter()?))
     }
 
     #[inline(always)]
     /// Returns `true` if the map contains no elements.
     pub fn is_empty(&self) -> bool {
-        self.range(..).is_empty()
+        self.try_is_empty().unwrap()
+    }
+
+    #[inline(always)]
+    /// Fallible version of [`is_empty`](Map::is_empty).
+    pub fn try_is_empty(&self) -> Result<bool, Error> {
+        self.try_range(..)?.try_is_empty()
+    }
+
+    #[inline]
+    /// Returns the number of elements in the map.
+    ///
+    /// LSM does not keep count, so this scans every key in the map; see
+    /// [`approximate_len`](Map::approximate_len) for a cheap estimate.
+    pub fn len(&self) -> usize {
+        self.try_len().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`len`](Map::len).
+    pub fn try_len(&self) -> Result<usize, Error> {
+        self.try_count_range(..)
+    }
+
+    #[inline]
+    /// Estimates the number of elements in the map from the size of the database, without scanning it.
+    ///
+    /// Maps holding only a few elements are counted exactly. Keys that were overwritten or removed
+    /// since the database was last merged still take up room, so the estimate tends to be high.
+    pub fn approximate_len(&self) -> usize {
+        self.try_approximate_len().unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`approximate_len`](Map::approximate_len).
+    pub fn try_approximate_len(&self) -> Result<usize, Error> {
+        self.tree.approximate_len()
+    }
+
+    #[inline]
+    /// Estimates the number of bytes taken by the elements within `range`, without scanning it.
+    ///
+    /// The estimate assumes that keys are evenly spread between the first and last keys of the map,
+    /// so it is best suited to ranges covering large parts of it, such as every key of a tenant.
+    pub fn approximate_size<'r, R: std::ops::RangeBounds<&'r [u8]>>(&self, range: R) -> u64 {
+        self.try_approximate_size(range).unwrap()
+    }
+
+    #[inline]
+    /// Fallible version of [`approximate_size`](Map::approximate_size).
+    pub fn try_approximate_size<'r, R: std::ops::RangeBounds<&'r [u8]>>(
+        &self,
+        range: R,
+    ) -> Result<u64, Error> {
+        self.tree.approximate_size(range)
+    }
+
+    /// Positions a cursor on `key`, if the map contains it.
+    fn lookup(&self, key: &[u8]) -> Result<Option<Bound<'_>>, Error> {
+        let bound = Bound::seek_in(&self.tree, key, Seek::EQ)?;
+
+        match bound.is_valid() {
+            true => Ok(Some(bound)),
+            false => Ok(None),
+        }
+    }
+}
+
+/// Wraps a freshly positioned bound as an entry, unless it ran off the end of the map.
+fn occupied(mut bound: Bound<'_>) -> Result<Option<OccupiedEntry<'_>>, Error> {
+    match found(bound.cursor())? {
+        Some(_) => Ok(Some(OccupiedEntry(bound))),
+        None => Ok(None), // the map is empty
+    }
+}
+
+impl<'m> IntoIterator for &'m Map {
+    type Item = (Vec<u8>, Vec<u8>);
+    type IntoIter = Iter<'m>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Iter<'m> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (Vec<u8>, Vec<u8>);
+    type IntoIter = IntoIter;
+
+    #[inline(always)]
+    fn into_iter(self) -> IntoIter {
+        self.try_into_iter().unwrap()
+    }
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> Extend<(K, V)> for Map {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.try_extend(iter).unwrap()
+    }
+}
+
+/// A `Map` opened without write access.
+///
+/// Only the lookup and iteration methods of [`Map`] are reachable through a `ReadOnlyMap`, as they are
+/// the ones that borrow the map immutably. The database file itself is opened read-only, so it can
+/// live in a directory the process cannot write to.
+///
+/// ```compile_fail
+/// # fn write(map: &mut lsm1::ReadOnlyMap) {
+/// map.insert(b"key", b"value");
+/// # }
+/// ```
+///
+/// A concurrent writer holding the database’s locks is reported as [`Error::Busy`], [`Error::Protocol`]
+/// or [`Error::Misuse`] by the `try_` methods; their panicking counterparts unwrap those errors.
+pub struct ReadOnlyMap {
+    map: Map,
+}
+
+impl ReadOnlyMap {
+    pub(crate) fn new(map: Map) -> Self {
+        ReadOnlyMap { map }
+    }
+
+    /// Closes the database, returning any error encountered while releasing the file.
+    pub fn close(self) -> Result<(), Error> {
+        self.map.close()
+    }
+}
+
+impl std::ops::Deref for ReadOnlyMap {
+    type Target = Map;
+
+    #[inline(always)]
+    fn deref(&self) -> &Map {
+        &self.map
+    }
+}
+
+impl<'m> IntoIterator for &'m ReadOnlyMap {
+    type Item = (Vec<u8>, Vec<u8>);
+    type IntoIter = Iter<'m>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Iter<'m> {
+        self.map.iter()
     }
 }
 
 /// An iterator over the entries of a `Map`.
-pub struct Iter<'e> {
-    range: RangeBounds<'e>,
+pub struct Iter<'m> {
+    range: RangeBounds<'m>,
+}
+
+impl<'m> Iter<'m> {
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.range.try_next()
+    }
+
+    #[inline]
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        self.range.try_next_back()
+    }
 }
 
-impl<'e> Iterator for Iter<'e> {
-    type Item = (&'e [u8], &'e [u8]);
+impl<'m> Iterator for Iter<'m> {
+    type Item = (Vec<u8>, Vec<u8>);
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -236,9 +1000,202 @@ impl<'e> Iterator for Iter<'e> {
     }
 }
 
-impl<'e> DoubleEndedIterator for Iter<'e> {
+impl<'m> DoubleEndedIterator for Iter<'m> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.range.next_back()
     }
 }
+
+/// An iterator over the values of a `Map`, in order by key.
+pub struct Values<'m>(Iter<'m>);
+
+impl<'m> Values<'m> {
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.try_next()?.map(|(_, value)| value))
+    }
+
+    #[inline]
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.try_next_back()?.map(|(_, value)| value))
+    }
+}
+
+impl<'m> Iterator for Values<'m> {
+    type Item = Vec<u8>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl<'m> DoubleEndedIterator for Values<'m> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.try_next_back().unwrap()
+    }
+}
+
+/// An owning iterator over the entries of a `Map`.
+///
+/// Entries are read a batch at a time from each end, with no cursor left open in between; each
+/// batch resumes after the last key read by the previous one.
+pub struct IntoIter {
+    map: Map,
+    start: std::ops::Bound<Vec<u8>>, // everything up to here has been read from the front
+    end: std::ops::Bound<Vec<u8>>,   // everything from here on has been read from the back
+    front: VecDeque<(Vec<u8>, Vec<u8>)>,
+    back: VecDeque<(Vec<u8>, Vec<u8>)>,
+}
+
+impl IntoIter {
+    fn new(map: Map) -> Self {
+        IntoIter {
+            map,
+            start: std::ops::Bound::Unbounded,
+            end: std::ops::Bound::Unbounded,
+            front: VecDeque::new(),
+            back: VecDeque::new(),
+        }
+    }
+
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        if self.front.is_empty() {
+            self.read(Direction::Next)?;
+        }
+
+        // once the ends meet, whatever the back has read ahead is next in line
+        Ok(self.front.pop_front().or_else(|| self.back.pop_front()))
+    }
+
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, Error> {
+        if self.back.is_empty() {
+            self.read(Direction::Prev)?;
+        }
+
+        Ok(self.back.pop_back().or_else(|| self.front.pop_back()))
+    }
+
+    /// Reads the next batch of entries between the two ends, from the end `direction` says.
+    fn read(&mut self, direction: Direction) -> Result<(), Error> {
+        let start = self.start.as_ref().map(Vec::as_slice);
+        let end = self.end.as_ref().map(Vec::as_slice);
+        let mut range = self.map.tree.range((start, end))?;
+
+        for _ in 0..BATCH {
+            match direction {
+                Direction::Next => match range.try_next()? {
+                    Some(entry) => self.front.push_back(entry),
+                    None => break,
+                },
+                Direction::Prev => match range.try_next_back()? {
+                    Some(entry) => self.back.push_front(entry),
+                    None => break,
+                },
+            }
+        }
+
+        match direction {
+            Direction::Next => {
+                if let Some((key, _)) = self.front.back() {
+                    self.start = std::ops::Bound::Excluded(key.clone());
+                }
+            }
+            Direction::Prev => {
+                if let Some((key, _)) = self.back.front() {
+                    self.end = std::ops::Bound::Excluded(key.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl DoubleEndedIterator for IntoIter {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.try_next_back().unwrap()
+    }
+}
+
+/// An owning iterator over the keys of a `Map`, in sorted order.
+pub struct IntoKeys(IntoIter);
+
+impl IntoKeys {
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.try_next()?.map(|(key, _)| key))
+    }
+
+    #[inline]
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.try_next_back()?.map(|(key, _)| key))
+    }
+}
+
+impl Iterator for IntoKeys {
+    type Item = Vec<u8>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl DoubleEndedIterator for IntoKeys {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.try_next_back().unwrap()
+    }
+}
+
+/// An owning iterator over the values of a `Map`, in order by key.
+pub struct IntoValues(IntoIter);
+
+impl IntoValues {
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.try_next()?.map(|(_, value)| value))
+    }
+
+    #[inline]
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        Ok(self.0.try_next_back()?.map(|(_, value)| value))
+    }
+}
+
+impl Iterator for IntoValues {
+    type Item = Vec<u8>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl DoubleEndedIterator for IntoValues {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.try_next_back().unwrap()
+    }
+}
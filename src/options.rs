@@ -0,0 +1,227 @@
+use crate::{Error, Map, ReadOnlyMap, Tree};
+
+use lsm_ext::{Config, Safety};
+
+/// Options and flags which can be used to configure how a `Map` is opened.
+///
+/// Every setting left unspecified keeps the LSM default. Out-of-range values are rejected by
+/// [`open`](OpenOptions::open) with [`Error::Misuse`] rather than being silently ignored, which is what
+/// `lsm_config` itself would do.
+///
+/// ```no_run
+/// use lsm1::{Map, Safety};
+///
+/// let map = Map::options().page_size(4096).safety(Safety::Full).open("example.db")?;
+/// # Ok::<(), lsm1::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OpenOptions {
+    auto_flush: Option<u32>,
+    page_size: Option<u32>,
+    block_size: Option<u32>,
+    safety: Option<Safety>,
+    auto_work: Option<bool>,
+    mmap: Option<u32>,
+    use_log: Option<bool>,
+    auto_merge: Option<u32>,
+    max_freelist: Option<u32>,
+    multiple_processes: Option<bool>,
+    auto_checkpoint: Option<u32>,
+    read_only: Option<bool>,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The size of the in-memory tree, in KB, at which it is flushed to disk. Between 0 and 1048576.
+    pub fn auto_flush(&mut self, kilobytes: u32) -> &mut Self {
+        self.auto_flush = Some(kilobytes);
+        self
+    }
+
+    /// The page size of a newly created database, in bytes. A power of two between 256 and 65536.
+    ///
+    /// Existing databases keep the page size they were created with.
+    pub fn page_size(&mut self, bytes: u32) -> &mut Self {
+        self.page_size = Some(bytes);
+        self
+    }
+
+    /// The block size of a newly created database, in KB. A power of two between 64 and 65536.
+    ///
+    /// Existing databases keep the block size they were created with.
+    pub fn block_size(&mut self, kilobytes: u32) -> &mut Self {
+        self.block_size = Some(kilobytes);
+        self
+    }
+
+    /// How often the database is synced to disk.
+    pub fn safety(&mut self, safety: Safety) -> &mut Self {
+        self.safety = Some(safety);
+        self
+    }
+
+    /// Whether writers merge segments themselves as part of their writes.
+    pub fn auto_work(&mut self, enabled: bool) -> &mut Self {
+        self.auto_work = Some(enabled);
+        self
+    }
+
+    /// Whether the database file is memory mapped: `0` disables it, `1` maps the whole file, and any
+    /// larger value is the maximum number of KB to map.
+    pub fn mmap(&mut self, value: u32) -> &mut Self {
+        self.mmap = Some(value);
+        self
+    }
+
+    /// Whether writes go through the log file.
+    pub fn use_log(&mut self, enabled: bool) -> &mut Self {
+        self.use_log = Some(enabled);
+        self
+    }
+
+    /// The minimum number of segments that are merged together. At least 2.
+    pub fn auto_merge(&mut self, segments: u32) -> &mut Self {
+        self.auto_merge = Some(segments);
+        self
+    }
+
+    /// The maximum number of free-list entries kept in the checkpoint. Between 2 and 24.
+    pub fn max_freelist(&mut self, entries: u32) -> &mut Self {
+        self.max_freelist = Some(entries);
+        self
+    }
+
+    /// Whether the database may be shared with other processes.
+    pub fn multiple_processes(&mut self, enabled: bool) -> &mut Self {
+        self.multiple_processes = Some(enabled);
+        self
+    }
+
+    /// The number of KB written to the database file after which a checkpoint is performed.
+    pub fn auto_checkpoint(&mut self, kilobytes: u32) -> &mut Self {
+        self.auto_checkpoint = Some(kilobytes);
+        self
+    }
+
+    /// Opens the database at `path` with the options specified by `self`.
+    pub fn open(&self, path: &str) -> Result<Map, Error> {
+        self.validate()?;
+
+        Ok(Map {
+            worker: None,
+            tree: Tree::open(path, self)?,
+        })
+    }
+
+    /// Opens the database at `path` read-only with the options specified by `self`.
+    pub fn open_read_only(&self, path: &str) -> Result<ReadOnlyMap, Error> {
+        // only reachable through here, so that a read-only database is always behind a `ReadOnlyMap`
+        let mut options = self.clone();
+        options.read_only = Some(true);
+
+        let map = options.open(path)?;
+        Ok(ReadOnlyMap::new(map))
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let power_of_two = |range: std::ops::RangeInclusive<u32>| {
+            move |value: u32| range.contains(&value) && value.is_power_of_two()
+        };
+
+        let checks = [
+            self.auto_flush.is_none_or(|kb| kb <= 1024 * 1024),
+            self.page_size.is_none_or(power_of_two(256..=65536)),
+            self.block_size.is_none_or(power_of_two(64..=65536)),
+            self.mmap.is_none_or(|value| value <= i32::MAX as u32),
+            self.auto_merge
+                .is_none_or(|n| (2..=i32::MAX as u32).contains(&n)),
+            self.max_freelist.is_none_or(|n| (2..=24).contains(&n)),
+            self.auto_checkpoint
+                .is_none_or(|kb| kb <= i32::MAX as u32 / 1024),
+        ];
+
+        match checks.iter().all(|&valid| valid) {
+            true => Ok(()),
+            false => Err(Error::Misuse),
+        }
+    }
+
+    /// The settings that can only be changed before the database is opened.
+    pub(crate) fn before_open(&self) -> impl Iterator<Item = (Config, i32)> {
+        [
+            (Config::PageSize, self.page_size.map(|n| n as i32)),
+            (Config::BlockSize, self.block_size.map(|n| n as i32)),
+            (Config::Mmap, self.mmap.map(|n| n as i32)),
+            (Config::UseLog, self.use_log.map(i32::from)),
+            (
+                Config::MultipleProcesses,
+                self.multiple_processes.map(i32::from),
+            ),
+            (Config::Readonly, self.read_only.map(i32::from)),
+        ]
+        .into_iter()
+        .filter_map(|(config, value)| Some((config, value?)))
+    }
+
+    /// The settings that can be changed at any time.
+    pub(crate) fn after_open(&self) -> impl Iterator<Item = (Config, i32)> {
+        [
+            (Config::AutoFlush, self.auto_flush.map(|n| n as i32)),
+            (Config::Safety, self.safety.map(|safety| safety as i32)),
+            (Config::AutoWork, self.auto_work.map(i32::from)),
+            (Config::AutoMerge, self.auto_merge.map(|n| n as i32)),
+            (Config::MaxFreelist, self.max_freelist.map(|n| n as i32)),
+            (
+                Config::AutoCheckpoint,
+                self.auto_checkpoint.map(|n| n as i32),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(config, value)| Some((config, value?)))
+    }
+}
+
+/// The configuration in effect for an open `Map`, as reported by [`Map::settings`].
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub auto_flush: u32,
+    pub page_size: u32,
+    pub block_size: u32,
+    pub safety: Safety,
+    pub auto_work: bool,
+    pub mmap: u32,
+    pub use_log: bool,
+    pub auto_merge: u32,
+    pub max_freelist: u32,
+    pub multiple_processes: bool,
+    pub auto_checkpoint: u32,
+    pub read_only: bool,
+}
+
+impl Settings {
+    pub(crate) fn new_in(tree: &Tree) -> Result<Self, Error> {
+        let get = |config| tree.config(config, -1); // negative values only query
+
+        Ok(Settings {
+            auto_flush: get(Config::AutoFlush)? as u32,
+            page_size: get(Config::PageSize)? as u32,
+            block_size: get(Config::BlockSize)? as u32,
+            safety: match get(Config::Safety)? {
+                0 => Safety::Off,
+                1 => Safety::Normal,
+                _ => Safety::Full,
+            },
+            auto_work: get(Config::AutoWork)? != 0,
+            mmap: get(Config::Mmap)? as u32,
+            use_log: get(Config::UseLog)? != 0,
+            auto_merge: get(Config::AutoMerge)? as u32,
+            max_freelist: get(Config::MaxFreelist)? as u32,
+            multiple_processes: get(Config::MultipleProcesses)? != 0,
+            auto_checkpoint: get(Config::AutoCheckpoint)? as u32,
+            read_only: get(Config::Readonly)? != 0,
+        })
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{Error, Map};
+
+use std::ops::Bound;
+
+/// A page of entries read by [`Map::page`] or [`Map::page_rev`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Page {
+    pub items: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Where to resume reading from, if there are more entries in the range.
+    pub next: Option<Token>,
+}
+
+/// An opaque position in a paginated scan, which can be stored or handed to a client and passed back
+/// to continue where the previous page left off.
+///
+/// The token records the last key read and the direction of the scan rather than a cursor, so it
+/// stays valid no matter what is inserted or removed between pages.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token {
+    reverse: bool,
+    last: Vec<u8>,
+}
+
+impl Token {
+    /// Encodes the token for storage or transport.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.last.len());
+        bytes.push(self.reverse as u8);
+        bytes.extend_from_slice(&self.last);
+        bytes
+    }
+
+    /// Decodes a token produced by [`to_bytes`](Token::to_bytes), failing with [`Error::Corrupt`]
+    /// if `bytes` is not one.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes.split_first() {
+            Some((&direction @ (0 | 1), last)) => Ok(Token {
+                reverse: direction == 1,
+                last: last.to_vec(),
+            }),
+            _ => Err(Error::Corrupt),
+        }
+    }
+}
+
+impl Map {
+    #[inline]
+    /// Reads up to `limit` entries of `range` in ascending key order, starting after the position
+    /// recorded by `token`, or at the start of the range if there is none.
+    pub fn page<'r, R>(&self, range: R, limit: usize, token: Option<Token>) -> Page
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        self.try_page(range, limit, token).unwrap()
+    }
+
+    /// Fallible version of [`page`](Map::page).
+    ///
+    /// Fails with [`Error::Misuse`] if `limit` is zero or `token` was returned by
+    /// [`page_rev`](Map::page_rev).
+    pub fn try_page<'r, R>(
+        &self,
+        range: R,
+        limit: usize,
+        token: Option<Token>,
+    ) -> Result<Page, Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        self.paginate(range, limit, token, false)
+    }
+
+    #[inline]
+    /// Reads up to `limit` entries of `range` in descending key order, starting before the position
+    /// recorded by `token`, or at the end of the range if there is none.
+    pub fn page_rev<'r, R>(&self, range: R, limit: usize, token: Option<Token>) -> Page
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        self.try_page_rev(range, limit, token).unwrap()
+    }
+
+    /// Fallible version of [`page_rev`](Map::page_rev).
+    ///
+    /// Fails with [`Error::Misuse`] if `limit` is zero or `token` was returned by [`page`](Map::page).
+    pub fn try_page_rev<'r, R>(
+        &self,
+        range: R,
+        limit: usize,
+        token: Option<Token>,
+    ) -> Result<Page, Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        self.paginate(range, limit, token, true)
+    }
+
+    fn paginate<'r, R>(
+        &self,
+        range: R,
+        limit: usize,
+        token: Option<Token>,
+        reverse: bool,
+    ) -> Result<Page, Error>
+    where
+        R: std::ops::RangeBounds<&'r [u8]>,
+    {
+        if limit == 0 || token.as_ref().is_some_and(|token| token.reverse != reverse) {
+            return Err(Error::Misuse);
+        }
+
+        let mut start = range.start_bound().cloned();
+        let mut end = range.end_bound().cloned();
+        if let Some(token) = &token {
+            match reverse {
+                false => start = after(start, &token.last),
+                true => end = before(end, &token.last),
+            }
+        }
+
+        let mut entries = self.try_range((start, end))?;
+        let mut next = || match reverse {
+            false => entries.try_next(),
+            true => entries.try_next_back(),
+        };
+
+        let mut items = Vec::with_capacity(limit.min(1024));
+        while items.len() < limit {
+            match next()? {
+                Some(entry) => items.push(entry),
+                None => return Ok(Page { items, next: None }),
+            }
+        }
+
+        // only hand out a token if there is something left to read with it
+        let next = match next()? {
+            Some(_) => items.last().map(|(key, _)| Token {
+                reverse,
+                last: key.clone(),
+            }),
+            None => None,
+        };
+
+        Ok(Page { items, next })
+    }
+}
+
+/// The tighter of `start` and a bound that excludes everything up to `last`.
+fn after<'k>(start: Bound<&'k [u8]>, last: &'k [u8]) -> Bound<&'k [u8]> {
+    match start {
+        Bound::Included(key) if key > last => start,
+        Bound::Excluded(key) if key >= last => start,
+        _ => Bound::Excluded(last),
+    }
+}
+
+/// The tighter of `end` and a bound that excludes everything from `last` on.
+fn before<'k>(end: Bound<&'k [u8]>, last: &'k [u8]) -> Bound<&'k [u8]> {
+    match end {
+        Bound::Included(key) if key < last => end,
+        Bound::Excluded(key) if key <= last => end,
+        _ => Bound::Excluded(last),
+    }
+}
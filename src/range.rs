@@ -1,129 +1,332 @@
 use lsm_ext::*;
 
-pub struct RangeBounds<'a> {
-    pub(crate) start_bound: Bound<'a>,
-    pub(crate) end_bound: Bound<'a>,
+use crate::Tree;
+
+/// A double-ended iterator over a sub-range of entries in a `Map`.
+///
+/// Keys and values are copied out of the database as they are visited; use a [`RangeCursor`] to
+/// scan without copying.
+pub struct RangeBounds<'m> {
+    pub(crate) cursor: RangeCursor<'m>,
 }
 
-impl<'a> RangeBounds<'a> {
+impl<'m> RangeBounds<'m> {
     pub(crate) fn new_in<'b>(
-        db: *mut lsm_db,
+        tree: &'m Tree,
+        range: impl std::ops::RangeBounds<&'b [u8]>,
+    ) -> Result<Self, Error> {
+        Ok(RangeBounds {
+            cursor: RangeCursor::new_in(tree, range)?,
+        })
+    }
+
+    // noinspection RsSelfConvention
+    pub fn is_empty(&mut self) -> bool {
+        self.try_is_empty().unwrap()
+    }
+
+    // noinspection RsSelfConvention
+    pub fn try_is_empty(&mut self) -> Result<bool, crate::Error> {
+        self.cursor.try_is_empty()
+    }
+
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, crate::Error> {
+        let entry = self.cursor.try_next()?;
+        Ok(entry.map(|(key, val)| (key.to_vec(), val.to_vec())))
+    }
+
+    #[inline]
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>, crate::Error> {
+        let entry = self.cursor.try_next_back()?;
+        Ok(entry.map(|(key, val)| (key.to_vec(), val.to_vec())))
+    }
+}
+
+impl<'m> Iterator for RangeBounds<'m> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+}
+
+impl<'m> DoubleEndedIterator for RangeBounds<'m> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.try_next_back().unwrap()
+    }
+}
+
+/// A double-ended iterator over the keys in a sub-range of a `Map`.
+///
+/// Only the keys are read from the database, so scanning them is cheap no matter how large the
+/// values are.
+pub struct Keys<'m> {
+    pub(crate) cursor: RangeCursor<'m>,
+}
+
+impl<'m> Keys<'m> {
+    pub(crate) fn new_in<'b>(
+        tree: &'m Tree,
+        range: impl std::ops::RangeBounds<&'b [u8]>,
+    ) -> Result<Self, Error> {
+        Ok(Keys {
+            cursor: RangeCursor::new_in(tree, range)?,
+        })
+    }
+
+    #[inline]
+    /// Fallible version of [`next`](Iterator::next).
+    pub fn try_next(&mut self) -> Result<Option<Vec<u8>>, crate::Error> {
+        Ok(self.cursor.try_next_key()?.map(<[u8]>::to_vec))
+    }
+
+    #[inline]
+    /// Fallible version of [`next_back`](DoubleEndedIterator::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<Vec<u8>>, crate::Error> {
+        Ok(self.cursor.try_next_back_key()?.map(<[u8]>::to_vec))
+    }
+
+    /// Counts the remaining keys without copying them.
+    pub fn try_count(mut self) -> Result<usize, crate::Error> {
+        let mut count = 0;
+        while self.cursor.try_next_key()?.is_some() {
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+impl<'m> Iterator for Keys<'m> {
+    type Item = Vec<u8>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().unwrap()
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.try_count().unwrap()
+    }
+}
+
+impl<'m> DoubleEndedIterator for Keys<'m> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.try_next_back().unwrap()
+    }
+}
+
+/// A lending iterator over a sub-range of entries in a `Map`.
+///
+/// The slices returned by [`next`](RangeCursor::next) point directly into the database’s buffers,
+/// so they borrow the cursor and must be released before it is advanced again.
+///
+/// ```compile_fail
+/// # fn scan(map: &lsm1::Map) {
+/// let mut cursor = map.range_cursor(..);
+/// let (key, _) = cursor.next().unwrap();
+/// cursor.next(); // `key` pointed into the entry the cursor just moved off of
+/// println!("{key:?}");
+/// # }
+/// ```
+pub struct RangeCursor<'m> {
+    pub(crate) start_bound: Bound<'m>,
+    pub(crate) end_bound: Bound<'m>,
+    // the entries last lent out; their cursors are moved off of them on the following call
+    front: bool,
+    back: bool,
+}
+
+impl<'m> RangeCursor<'m> {
+    pub(crate) fn new_in<'b>(
+        tree: &'m Tree,
         range: impl std::ops::RangeBounds<&'b [u8]>,
     ) -> Result<Self, Error> {
         let lhs = range.start_bound().cloned();
         let rhs = range.end_bound().cloned();
 
-        let start_bound = Bound::new_in(db, lhs, Direction::Next)?;
-        let end_bound = Bound::new_in(db, rhs, Direction::Prev)?;
+        let start_bound = Bound::new_in(tree, lhs, Direction::Next)?;
+        let end_bound = Bound::new_in(tree, rhs, Direction::Prev)?;
 
-        Ok(RangeBounds {
+        Ok(RangeCursor {
             start_bound,
             end_bound,
+            front: false,
+            back: false,
         })
     }
 
     // noinspection RsSelfConvention
     pub fn is_empty(&mut self) -> bool {
-        self.start_bound.cursor().ok().is_none()
+        self.try_is_empty().unwrap()
     }
-}
 
-impl<'a> Iterator for RangeBounds<'a> {
-    type Item = (&'a [u8], &'a [u8]);
+    // noinspection RsSelfConvention
+    pub fn try_is_empty(&mut self) -> Result<bool, crate::Error> {
+        if self.front {
+            return Ok(false); // an entry is being lent out
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let cursor = self.start_bound.cursor().ok()?;
+        Ok(self.within(Direction::Next)? == false)
+    }
 
-        unsafe {
-            if lsm_csr_valid(cursor) == false {
-                return None;
-            }
+    #[inline]
+    /// Advances the cursor and returns the next entry in the range.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.try_next().unwrap()
+    }
 
-            if self.end_bound.is_bounded() {
-                let mut cmp = 0;
-                let key = self.end_bound.key().ok()?;
+    #[inline]
+    /// Advances the cursor from the back and returns the next entry at the end of the range.
+    pub fn next_back(&mut self) -> Option<(&[u8], &[u8])> {
+        self.try_next_back().unwrap()
+    }
 
-                lsm_csr_cmp(cursor, key.as_ptr(), key.len() as u32, &mut cmp)
-                    .ok()
-                    .ok()?;
+    /// Fallible version of [`next`](RangeCursor::next).
+    pub fn try_next(&mut self) -> Result<Option<(&[u8], &[u8])>, crate::Error> {
+        match self.advance(Direction::Next)? {
+            true => Ok(Some((self.start_bound.key()?, self.start_bound.val()?))),
+            false => Ok(None),
+        }
+    }
 
-                if cmp >= 0 {
-                    return None;
-                }
-            }
+    /// Fallible version of [`next_back`](RangeCursor::next_back).
+    pub fn try_next_back(&mut self) -> Result<Option<(&[u8], &[u8])>, crate::Error> {
+        match self.advance(Direction::Prev)? {
+            true => Ok(Some((self.end_bound.key()?, self.end_bound.val()?))),
+            false => Ok(None),
+        }
+    }
 
-            let key = self.start_bound.key().ok()?;
+    #[inline]
+    /// Advances the cursor and returns the next key in the range, without reading its value.
+    pub fn next_key(&mut self) -> Option<&[u8]> {
+        self.try_next_key().unwrap()
+    }
 
-            let value = self
-                .start_bound
-                .val()
-                .and_then(|val| {
-                    lsm_csr_next(cursor).ok().expect("next");
-                    Ok(val)
-                })
-                .expect("val");
+    #[inline]
+    /// Advances the cursor from the back and returns the next key at the end of the range, without
+    /// reading its value.
+    pub fn next_back_key(&mut self) -> Option<&[u8]> {
+        self.try_next_back_key().unwrap()
+    }
 
-            Some((key, value))
+    /// Fallible version of [`next_key`](RangeCursor::next_key).
+    pub fn try_next_key(&mut self) -> Result<Option<&[u8]>, crate::Error> {
+        match self.advance(Direction::Next)? {
+            true => Ok(Some(self.start_bound.key()?)),
+            false => Ok(None),
         }
     }
-}
 
-impl<'a> DoubleEndedIterator for RangeBounds<'a> {
-    fn next_back(&mut self) -> Option<Self::Item> {
-        let cursor = self.end_bound.cursor().ok()?;
+    /// Fallible version of [`next_back_key`](RangeCursor::next_back_key).
+    pub fn try_next_back_key(&mut self) -> Result<Option<&[u8]>, crate::Error> {
+        match self.advance(Direction::Prev)? {
+            true => Ok(Some(self.end_bound.key()?)),
+            false => Ok(None),
+        }
+    }
 
+    /// Moves the cursor going in `direction` off of the entry it last lent out, returning whether it
+    /// is on one it can lend next.
+    fn advance(&mut self, direction: Direction) -> Result<bool, crate::Error> {
         unsafe {
-            if lsm_csr_valid(cursor) == false {
-                return None;
+            match direction {
+                Direction::Next if std::mem::take(&mut self.front) => {
+                    lsm_csr_next(self.start_bound.cursor()?).ok()?
+                }
+                Direction::Prev if std::mem::take(&mut self.back) => {
+                    lsm_csr_prev(self.end_bound.cursor()?).ok()?
+                }
+                _ => {}
             }
+        }
 
-            if self.start_bound.is_bounded() {
-                let mut cmp = 0;
-                let key = self.start_bound.key().ok()?;
+        let within = self.within(direction)?;
+        match direction {
+            Direction::Next => self.front = within,
+            Direction::Prev => self.back = within,
+        }
 
-                lsm_csr_cmp(cursor, key.as_ptr(), key.len() as u32, &mut cmp)
-                    .ok()
-                    .ok()?;
+        Ok(within)
+    }
 
-                if cmp <= 0 {
-                    return None;
-                }
-            }
+    /// Whether the cursor moving in `direction` is on an entry that the other one has not yet reached.
+    ///
+    /// Each cursor stays on the entry it last lent out until it is advanced again, so the two may only
+    /// meet on an entry that neither has returned.
+    fn within(&mut self, direction: Direction) -> Result<bool, crate::Error> {
+        let (this, other, other_lent) = match direction {
+            Direction::Next => (&mut self.start_bound, &self.end_bound, self.back),
+            Direction::Prev => (&mut self.end_bound, &self.start_bound, self.front),
+        };
+
+        let Some(cursor) = found(this.cursor())? else {
+            return Ok(false);
+        };
+
+        if other.is_bounded() == false {
+            return Ok(true); // the other end has not been moved yet
+        }
 
-            let key = self.start_bound.key().ok()?;
+        let Some(key) = found(other.key())? else {
+            return Ok(false); // the other end has run past this one
+        };
 
-            let value = self
-                .end_bound
-                .val()
-                .and_then(|val| {
-                    lsm_csr_next(cursor).ok().expect("next");
-                    Ok(val)
-                })
-                .expect("val");
+        let mut cmp = 0;
+        unsafe { lsm_csr_cmp(cursor, key.as_ptr(), key.len() as u32, &mut cmp).ok()? };
 
-            Some((key, value))
-        }
+        Ok(match (cmp, direction) {
+            (0, _) => other_lent == false,
+            (cmp, Direction::Next) => cmp < 0,
+            (cmp, Direction::Prev) => cmp > 0,
+        })
+    }
+}
+
+/// The smallest key that sorts after every key starting with `prefix`, if there is one; prefixes
+/// made up of nothing but `0xFF` bytes have no such successor.
+pub(crate) fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let len = prefix.iter().rposition(|&byte| byte != 0xFF)?;
+
+    let mut end = prefix[..=len].to_vec();
+    end[len] += 1;
+    Some(end)
+}
+
+/// Maps the “cursor is not pointing at anything” error onto `None`.
+pub(crate) fn found<T>(result: Result<T, Error>) -> Result<Option<T>, crate::Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NoEnt) => Ok(None),
+        Err(error) => Err(error.into()),
     }
 }
 
-use std::marker::PhantomData;
 use std::ptr::null_mut;
 use std::slice::from_raw_parts;
 
-pub(crate) enum Bound<'a> {
-    Included(*mut lsm_db, *mut lsm_cursor),
+pub(crate) enum Bound<'m> {
+    Included(&'m Tree, *mut lsm_cursor),
     Unbounded(
-        *mut lsm_db,
+        &'m Tree,
         unsafe extern "C" fn(cursor: *mut lsm_cursor) -> Error,
-        PhantomData<&'a u8>,
     ),
 }
 
-impl<'a> Bound<'a> {
+impl<'m> Bound<'m> {
     #[inline(never)]
     /// Unbounded bounds refrain from allocating a cursor until it is needed.
     pub(crate) fn new_in(
-        db: *mut lsm_db,
+        tree: &'m Tree,
         bound: std::ops::Bound<&[u8]>,
         direction: Direction,
     ) -> Result<Self, Error> {
@@ -139,17 +342,17 @@ impl<'a> Bound<'a> {
             let result = (|| -> Result<Self, Error> {
                 match bound {
                     std::ops::Bound::Unbounded => Ok(match direction {
-                        Direction::Next => Bound::Unbounded(db, lsm_csr_first, Default::default()),
-                        Direction::Prev => Bound::Unbounded(db, lsm_csr_last, Default::default()),
+                        Direction::Next => Bound::Unbounded(tree, lsm_csr_first),
+                        Direction::Prev => Bound::Unbounded(tree, lsm_csr_last),
                     }),
                     std::ops::Bound::Included(key) => {
-                        lsm_csr_open(db, &mut cursor).ok()?;
+                        cursor = tree.acquire()?;
                         lsm_csr_seek(cursor, key.as_ptr(), key.len() as u32, seek()).ok()?;
 
-                        Ok(Bound::Included(db, cursor))
+                        Ok(Bound::Included(tree, cursor))
                     }
                     std::ops::Bound::Excluded(key) => {
-                        lsm_csr_open(db, &mut cursor).ok()?;
+                        cursor = tree.acquire()?;
                         lsm_csr_seek(cursor, key.as_ptr(), key.len() as u32, seek()).ok()?;
 
                         let mut cmp = 0;
@@ -161,51 +364,82 @@ impl<'a> Bound<'a> {
                             _ => {}
                         };
 
-                        Ok(Bound::Included(db, cursor))
+                        Ok(Bound::Included(tree, cursor))
                     }
                 }
             })();
 
             // close cursor on errors
-            result.map_err(|error| {
+            result.inspect_err(|_| {
                 let _ = lsm_csr_close(cursor); // ignores null ptrs properly
-                error
             })
         }
     }
 
-    fn db(&self) -> *mut lsm_db {
+    /// Takes a cursor that is not positioned anywhere in particular yet.
+    pub(crate) fn open_in(tree: &'m Tree) -> Result<Self, Error> {
+        Ok(Bound::Included(tree, tree.acquire()?))
+    }
+
+    /// Positions a new cursor with an explicit seek mode, such as `Seek::LE` or `Seek::EQ`.
+    pub(crate) fn seek_in(tree: &'m Tree, key: &[u8], seek: Seek) -> Result<Self, Error> {
+        let mut bound = Bound::open_in(tree)?;
+        bound.seek(key, seek)?;
+
+        Ok(bound)
+    }
+
+    /// Repositions the cursor, returning whether it landed on an entry.
+    pub(crate) fn seek(&mut self, key: &[u8], seek: Seek) -> Result<bool, Error> {
+        let cursor = self.raw()?;
+        unsafe { lsm_csr_seek(cursor, key.as_ptr(), key.len() as u32, seek).ok()? };
+
+        Ok(self.is_valid())
+    }
+
+    pub(crate) fn tree(&self) -> &'m Tree {
         match self {
-            Bound::Included(db, ..) => *db,
-            Bound::Unbounded(db, ..) => *db,
+            Bound::Included(tree, ..) => tree,
+            Bound::Unbounded(tree, ..) => tree,
         }
     }
 
-    pub(crate) fn cursor(&mut self) -> Result<*mut lsm_cursor, Error> {
+    /// The underlying cursor, whether or not it is on an entry.
+    pub(crate) fn raw(&mut self) -> Result<*mut lsm_cursor, Error> {
         unsafe {
-            let cursor = match self {
-                Bound::Included(_, cursor) => *cursor,
-                Bound::Unbounded(db, position, ..) => {
-                    let mut cursor = null_mut();
-                    lsm_csr_open(*db, &mut cursor).ok()?;
-                    position(cursor).ok().map_err(|error| {
+            match self {
+                Bound::Included(_, cursor) => Ok(*cursor),
+                Bound::Unbounded(tree, position) => {
+                    let cursor = tree.acquire()?;
+                    position(cursor).ok().inspect_err(|_| {
                         let _ = lsm_csr_close(cursor);
-                        error
                     })?;
 
                     // Unbounded bounds are lazily loaded; right here
-                    *self = Bound::Included(*db, cursor);
-                    cursor
+                    *self = Bound::Included(tree, cursor);
+                    Ok(cursor)
                 }
-            };
-
-            match lsm_csr_valid(cursor) {
-                true => Ok(cursor),
-                false => Err(Error::NoEnt),
             }
         }
     }
 
+    pub(crate) fn cursor(&mut self) -> Result<*mut lsm_cursor, Error> {
+        let cursor = self.raw()?;
+
+        match unsafe { lsm_csr_valid(cursor) } {
+            true => Ok(cursor),
+            false => Err(Error::NoEnt),
+        }
+    }
+
+    /// Whether the cursor is loaded and on an entry.
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            Bound::Included(_, cursor) => unsafe { lsm_csr_valid(*cursor) },
+            Bound::Unbounded(..) => false,
+        }
+    }
+
     pub fn is_bounded(&self) -> bool {
         match &self {
             Bound::Included(..) => true,
@@ -213,13 +447,18 @@ impl<'a> Bound<'a> {
         }
     }
 
-    pub fn key(&mut self) -> Result<&'a [u8], Error> {
+    /// The key under the cursor; only valid until the cursor is moved or the database is written to.
+    pub fn key(&self) -> Result<&[u8], Error> {
         let mut ptr: *const u8 = null_mut();
         let mut len: u32 = 0;
 
         unsafe {
-            lsm_csr_key(self.cursor()?, &mut ptr, &mut len).ok()?;
-            if ptr == null_mut() {
+            let Bound::Included(_, cursor) = self else {
+                return Err(Error::NoEnt); // not positioned yet
+            };
+
+            lsm_csr_key(*cursor, &mut ptr, &mut len).ok()?;
+            if ptr.is_null() {
                 Err(Error::NoEnt)
             } else {
                 Ok(from_raw_parts(ptr, len as usize))
@@ -227,13 +466,18 @@ impl<'a> Bound<'a> {
         }
     }
 
-    pub fn val(&mut self) -> Result<&'a [u8], Error> {
+    /// The value under the cursor; only valid until the cursor is moved or the database is written to.
+    pub fn val(&self) -> Result<&[u8], Error> {
         let mut ptr: *const u8 = null_mut();
         let mut len: u32 = 0;
 
         unsafe {
-            lsm_csr_value(self.cursor()?, &mut ptr, &mut len).ok()?;
-            if ptr == null_mut() {
+            let Bound::Included(_, cursor) = self else {
+                return Err(Error::NoEnt); // not positioned yet
+            };
+
+            lsm_csr_value(*cursor, &mut ptr, &mut len).ok()?;
+            if ptr.is_null() {
                 Err(Error::NoEnt)
             } else {
                 Ok(from_raw_parts(ptr, len as usize))
@@ -241,32 +485,19 @@ impl<'a> Bound<'a> {
         }
     }
 
-    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
-        let ptr = key.as_ptr();
-        let len = key.len() as u32;
-
-        unsafe {
-            lsm_insert(self.db(), ptr, len, value.as_ptr(), value.len() as u32).ok()?;
-        }
-
-        Ok(())
+    /// Lets go of the cursor, leaving the bound unloaded; LSM only lets the tree be written to once
+    /// every cursor reading from it is closed, or the write may fail with `Busy`.
+    pub(crate) fn unload(&mut self) {
+        let tree = self.tree();
+        *self = Bound::Unbounded(tree, lsm_csr_first);
     }
+}
 
-    pub fn remove(&mut self) -> Result<(), Error> {
-        let key = self.key()?;
-        let ptr = key.as_ptr();
-        let len = key.len() as u32;
-
-        unsafe {
-            lsm_delete(self.db(), ptr, len).ok()?;
+impl<'m> Drop for Bound<'m> {
+    fn drop(&mut self) {
+        if let Bound::Included(tree, cursor) = self {
+            tree.release(*cursor);
         }
-
-        Ok(())
-    }
-
-    pub fn replace(&mut self, value: &[u8]) -> Result<(), Error> {
-        let key = self.key()?;
-        self.insert(key, value)
     }
 }
 
@@ -0,0 +1,62 @@
+use crate::Map;
+
+/// A consistent view of a `Map`, as taken by [`Map::snapshot`].
+///
+/// The snapshot dereferences to the map, so every read is available through it. Its reads share a
+/// single cursor that is kept open between calls, which makes point lookups considerably cheaper,
+/// and see the database as it was on the first of them: what other connections commit since is
+/// only seen after a [`refresh`](Snapshot::refresh), or once the snapshot is dropped.
+///
+/// The snapshot borrows the map, so it has to be dropped before the map can be written to.
+///
+/// ```compile_fail
+/// # fn import(map: &mut lsm1::Map) -> Result<(), lsm1::Error> {
+/// let snapshot = map.snapshot();
+/// map.try_insert(b"key", b"value")?; // `snapshot` is still open
+/// snapshot.try_get(b"key")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Snapshot<'m> {
+    map: &'m Map,
+}
+
+impl<'m> Snapshot<'m> {
+    /// Lets go of the database as it was, so that the next read sees whatever has been committed
+    /// since.
+    pub fn refresh(&self) {
+        self.map.tree.reset();
+    }
+}
+
+impl<'m> std::ops::Deref for Snapshot<'m> {
+    type Target = Map;
+
+    #[inline(always)]
+    fn deref(&self) -> &Map {
+        self.map
+    }
+}
+
+impl<'m> Drop for Snapshot<'m> {
+    fn drop(&mut self) {
+        let tree = &self.map.tree;
+        tree.pins.set(tree.pins.get() - 1);
+
+        if tree.pins.get() + tree.depth.get() == 0 {
+            tree.reset();
+        }
+    }
+}
+
+impl Map {
+    /// Takes a [`Snapshot`] of the map, whose reads reuse one cursor instead of opening their own.
+    ///
+    /// Reads made directly on the map while the snapshot is open share its cursor as well.
+    pub fn snapshot(&self) -> Snapshot<'_> {
+        let tree = &self.tree;
+        tree.pins.set(tree.pins.get() + 1);
+
+        Snapshot { map: self }
+    }
+}
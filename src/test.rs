@@ -20,8 +20,1023 @@ fn property_testing(insertions: Vec<u32>, deletions: Vec<u32>) {
         lsm.remove(n.to_be_bytes().as_ref());
     }
 
+    assert_equal(map, lsm.iter());
+}
+
+#[quickcheck]
+fn reverse_iteration(keys: Vec<u16>, start: u16, end: u16) {
+    let map: BTreeMap<_, _> = keys
+        .iter()
+        .map(|n| (n.to_be_bytes().to_vec(), n.to_le_bytes().to_vec()))
+        .collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for n in keys.iter() {
+        lsm.insert(n.to_be_bytes().as_ref(), n.to_le_bytes().as_ref());
+    }
+
+    assert_equal(map.clone().into_iter().rev(), lsm.iter().rev());
+    assert_eq!(
+        map.last_key_value().map(|(k, v)| (k.clone(), v.clone())),
+        lsm.last_key_value()
+    );
+
+    if start <= end {
+        let (start, end) = (start.to_be_bytes(), end.to_be_bytes());
+        let expected = map.range(start.to_vec()..end.to_vec()).rev();
+        assert_equal(
+            expected.map(|(k, v)| (k.clone(), v.clone())),
+            lsm.range(start.as_ref()..end.as_ref()).rev(),
+        );
+    }
+}
+
+#[quickcheck]
+fn interleaved_iteration(keys: Vec<u16>, directions: Vec<bool>) {
+    let map: BTreeMap<_, _> = keys
+        .iter()
+        .map(|n| (n.to_be_bytes().to_vec(), n.to_le_bytes().to_vec()))
+        .collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for n in keys.iter() {
+        lsm.insert(n.to_be_bytes().as_ref(), n.to_le_bytes().as_ref());
+    }
+
+    let mut expected = map.into_iter();
+    let mut actual = lsm.iter();
+
+    // keep going past the point where both ends meet, to check that neither yields anything more
+    let alternating = (0..=keys.len()).map(|n| n % 2 == 0);
+    for forward in directions.into_iter().chain(alternating) {
+        match forward {
+            true => assert_eq!(expected.next(), actual.next()),
+            false => assert_eq!(expected.next_back(), actual.next_back()),
+        }
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn dropping_releases_resources() {
+    let open_files = || std::fs::read_dir("/proc/self/fd").unwrap().count();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut lsm = crate::map::Map::new(path).unwrap();
+    for n in 0u32..64 {
+        lsm.insert(n.to_be_bytes().as_ref(), n.to_le_bytes().as_ref());
+    }
+    lsm.close().unwrap();
+
+    let before = open_files();
+    for n in 0u32..4096 {
+        let lsm = crate::map::Map::new(path).unwrap();
+        assert!(lsm.get(n.to_be_bytes().as_ref()).is_some() == (n < 64));
+        assert_eq!(lsm.iter().count(), 64);
+        assert_eq!(lsm.range(..).rev().take(1).count(), 1);
+    }
+
+    // other tests may be running in parallel, so allow for a little slack
+    assert!(open_files() < before + 32);
+}
+
+#[test]
+fn options_are_applied() {
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let map = crate::Map::options()
+        .page_size(8192)
+        .block_size(2048)
+        .safety(crate::Safety::Full)
+        .auto_work(false)
+        .max_freelist(8)
+        .open(path)
+        .unwrap();
+
+    let settings = map.settings().unwrap();
+    assert_eq!(settings.page_size, 8192);
+    assert_eq!(settings.block_size, 2048);
+    assert_eq!(settings.safety, crate::Safety::Full);
+    assert!(settings.auto_work == false);
+    assert_eq!(settings.max_freelist, 8);
+
+    let invalid = crate::Map::options().page_size(1000).open(path);
+    assert!(matches!(invalid, Err(crate::Error::Misuse)));
+}
+
+#[test]
+fn read_only_maps_can_be_read() {
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut lsm = crate::Map::new(path).unwrap();
+    lsm.insert(b"key", b"value");
+    lsm.close().unwrap();
+
+    let lsm = crate::Map::open_read_only(path).unwrap();
+    assert_eq!(&*lsm.get(b"key").unwrap(), b"value");
+    assert_eq!(lsm.iter().count(), 1);
+    assert!(lsm.settings().unwrap().read_only);
+}
+
+#[test]
+#[cfg(unix)]
+fn read_only_maps_open_in_unwritable_directories() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("lsm1-read-only-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("db");
+    let path = path.to_str().unwrap();
+
+    let mut lsm = crate::Map::new(path).unwrap();
+    lsm.insert(b"key", b"value");
+    lsm.close().unwrap();
+
+    let permissions = |mode| std::fs::Permissions::from_mode(mode);
+    std::fs::set_permissions(&dir, permissions(0o555)).unwrap();
+
+    // permissions do not stop root, in which case there is nothing to test
+    let writable = std::fs::write(dir.join("probe"), b"").is_ok();
+    let result = crate::Map::open_read_only(path).map(|lsm| lsm.get_key_value(b"key"));
+    std::fs::set_permissions(&dir, permissions(0o755)).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    if writable == false {
+        assert_eq!(result, Ok(Some((b"key".to_vec(), b"value".to_vec()))));
+    }
+}
+
+#[test]
+fn read_only_maps_report_concurrent_writers() {
+    use crate::Error::*;
+
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut writer = crate::Map::new(path).unwrap();
+    writer.insert(b"key", b"value");
+    let mut tx = writer.transaction().unwrap();
+    tx.insert(b"pending", b"value");
+
+    // whether the reader gets through or not, it must not panic
+    let result = crate::Map::open_read_only(path).and_then(|lsm| lsm.try_get_key_value(b"key"));
+    match result {
+        Ok(entry) => assert_eq!(entry, Some((b"key".to_vec(), b"value".to_vec()))),
+        Err(error) => assert!(matches!(error, Busy | Protocol | Misuse), "{error:?}"),
+    }
+
+    tx.commit().unwrap();
+}
+
+#[test]
+fn transactions_commit_or_leave_no_trace() {
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut lsm = crate::Map::new(path).unwrap();
+
+    let mut tx = lsm.transaction().unwrap();
+    tx.insert(b"committed", b"1");
+    tx.commit().unwrap();
+
+    let mut tx = lsm.transaction().unwrap();
+    tx.insert(b"rolled back", b"2");
+    tx.remove(b"committed");
+    drop(tx);
+
+    let failed = lsm.transact(|tx| {
+        tx.insert(b"failed", b"3");
+        Err::<(), _>(crate::Error::Error)
+    });
+    assert_eq!(failed, Err(crate::Error::Error));
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let mut tx = lsm.transaction().unwrap();
+        tx.insert(b"panicked", b"4");
+        panic!("abandoning the transaction");
+    }));
+    assert!(panicked.is_err());
+
+    lsm.close().unwrap();
+
+    let lsm = crate::Map::new(path).unwrap();
+    assert_equal(lsm.keys(), [b"committed".to_vec()]);
+}
+
+#[test]
+fn savepoints_roll_back_independently() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    let mut tx = lsm.transaction().unwrap();
+    tx.insert(b"a", b"1");
+
+    let mut savepoint = tx.savepoint().unwrap();
+    savepoint.insert(b"b", b"2");
+    savepoint.release().unwrap();
+
+    let mut savepoint = tx.savepoint().unwrap();
+    savepoint.insert(b"c", b"3");
+    savepoint.remove(b"a");
+
+    let mut nested = savepoint.savepoint().unwrap();
+    nested.insert(b"d", b"4");
+    nested.release().unwrap();
+
+    savepoint.rollback().unwrap();
+    tx.insert(b"e", b"5");
+    tx.commit().unwrap();
+
+    assert_equal(lsm.keys(), [b"a", b"b", b"e"].map(|key| key.to_vec()));
+}
+
+#[test]
+fn write_batches_apply_in_order() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    for key in [b"a", b"b", b"c", b"d", b"e"] {
+        lsm.insert(key, b"old");
+    }
+
+    let batch = crate::WriteBatch::new()
+        .put(b"c", b"dropped")
+        .delete_range(b"b".as_ref()..=b"c".as_ref())
+        .put(b"c", b"new")
+        .delete(b"e")
+        .put(b"f", b"new");
+
+    assert_eq!(batch.len(), 4);
+    assert_eq!(
+        crate::WriteBatch::from_bytes(&batch.to_bytes()),
+        Ok(batch.clone())
+    );
+    assert!(crate::WriteBatch::from_bytes(&[2, 4]).is_err());
+
+    lsm.write(batch).unwrap();
+    assert_equal(lsm.keys(), [b"a", b"c", b"d", b"f"].map(|key| key.to_vec()));
+    assert_eq!(&*lsm.get(b"c").unwrap(), b"new");
+
+    lsm.write(crate::WriteBatch::new().delete_range(b"b".as_ref()..))
+        .unwrap();
+    assert_equal(lsm.keys(), [b"a".to_vec()]);
+}
+
+#[quickcheck]
+fn removing_ranges_matches_btree_map(
+    keys: Vec<u8>,
+    start: Option<(u8, bool)>,
+    end: Option<(u8, bool)>,
+) {
+    use std::ops::Bound::*;
+
+    let bound = |bound: Option<(u8, bool)>| match bound {
+        Some((key, true)) => Included(vec![key]),
+        Some((key, false)) => Excluded(vec![key]),
+        None => Unbounded,
+    };
+
+    let mut map = BTreeMap::<Vec<u8>, Vec<u8>>::new();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    for key in keys {
+        map.insert(vec![key], vec![key]);
+        lsm.insert(&[key], &[key]);
+    }
+
+    let (start, end) = (bound(start), bound(end));
+    map.retain(|key, _| {
+        std::ops::RangeBounds::contains(&(start.clone(), end.clone()), key) == false
+    });
+
+    let (lhs, rhs) = (start.as_ref(), end.as_ref());
+    lsm.remove_range((lhs.map(Vec::as_slice), rhs.map(Vec::as_slice)));
+    assert_equal(map, lsm.iter());
+
+    lsm.clear();
+    assert!(lsm.is_empty());
+}
+
+#[quickcheck]
+fn prefixes_match_btree_map(keys: Vec<Vec<u8>>, prefix: Vec<u8>) {
+    let prefix: Vec<u8> = prefix.into_iter().map(|byte| byte | 0xFC).take(2).collect(); // favour 0xFF
+    let mut map: BTreeMap<_, _> = keys.iter().map(|key| (key.clone(), key.clone())).collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for key in keys.iter() {
+        lsm.insert(key, key);
+    }
+
+    let expected = map.iter().filter(|(key, _)| key.starts_with(&prefix));
+    assert_equal(
+        expected.clone().map(|(k, v)| (k.clone(), v.clone())),
+        lsm.prefix(&prefix),
+    );
     assert_equal(
-        map.iter().map(|(k, v)| (k.as_slice(), v.as_slice())),
-        lsm.iter(),
+        expected.rev().map(|(k, v)| (k.clone(), v.clone())),
+        lsm.prefix(&prefix).rev(),
+    );
+
+    map.retain(|key, _| key.starts_with(&prefix) == false);
+    lsm.remove_prefix(&prefix);
+    assert_equal(map, lsm.iter());
+}
+
+#[quickcheck]
+fn nearest_lookups_match_btree_map(keys: Vec<u8>, removals: Vec<u8>, range: (u8, u8), key: u8) {
+    use std::ops::Bound::*;
+
+    let mut map: BTreeMap<_, _> = keys.iter().map(|&n| (vec![n], vec![n, n])).collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for &n in keys.iter() {
+        lsm.insert(&[n], &[n, n]);
+    }
+
+    // delete markers and range tombstones must not be mistaken for entries
+    for &n in removals.iter() {
+        map.remove(&vec![n]);
+        lsm.remove(&[n]);
+    }
+    let (start, end) = (range.0.min(range.1), range.0.max(range.1));
+    map.retain(|k, _| (start..end).contains(&k[0]) == false);
+    lsm.remove_range([start].as_slice()..[end].as_slice());
+
+    let key = vec![key];
+    let owned = |(k, v): (&Vec<u8>, &Vec<u8>)| (k.clone(), v.clone());
+
+    assert_eq!(
+        map.range(..=key.clone()).next_back().map(owned),
+        lsm.floor(&key)
+    );
+    assert_eq!(
+        map.range(..=key.clone())
+            .next_back()
+            .map(|(k, _)| k.clone()),
+        lsm.floor_key(&key)
+    );
+    assert_eq!(
+        map.range(key.clone()..).next().map(owned),
+        lsm.ceiling(&key)
+    );
+    assert_eq!(
+        map.range(..key.clone()).next_back().map(owned),
+        lsm.predecessor(&key)
+    );
+    assert_eq!(
+        map.range((Excluded(key.clone()), Unbounded))
+            .next()
+            .map(owned),
+        lsm.successor(&key)
+    );
+}
+
+#[test]
+fn cursors_stay_positioned_across_writes() {
+    use crate::Seek;
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for key in [b"b", b"d", b"f"] {
+        lsm.insert(key, key);
+    }
+
+    let mut cursor = lsm.cursor();
+    assert_eq!(cursor.seek(b"c", Seek::LEFast), Err(crate::Error::Misuse));
+    assert!(cursor.seek(b"c", Seek::LE).unwrap());
+    assert_eq!(cursor.key(), Some(b"b".as_ref()));
+    assert_eq!(
+        cursor.peek_next().unwrap(),
+        Some((b"d".to_vec(), b"d".to_vec()))
+    );
+    assert!(cursor.last().unwrap());
+    assert!(cursor.prev().unwrap());
+    assert_eq!(cursor.value(), Some(b"d".as_ref()));
+    drop(cursor);
+
+    let mut cursor = lsm.cursor_mut();
+    assert!(cursor.seek(b"d", Seek::GE).unwrap());
+    cursor.insert_before(b"c", b"c").unwrap();
+    cursor.insert_after(b"e", b"e").unwrap();
+    assert_eq!(cursor.insert_after(b"g", b"g"), Err(crate::Error::Misuse));
+    cursor.replace_value(b"D").unwrap();
+    assert_eq!(cursor.key(), Some(b"d".as_ref()));
+
+    assert_eq!(
+        cursor.remove_current().unwrap(),
+        Some((b"d".to_vec(), b"D".to_vec()))
+    );
+    assert_eq!(cursor.key(), Some(b"e".as_ref()));
+
+    drop(cursor);
+    assert_equal(lsm.keys(), [b"b", b"c", b"e", b"f"].map(|key| key.to_vec()));
+}
+
+#[quickcheck]
+fn batched_lookups_match_btree_map(keys: Vec<u8>, lookups: Vec<u8>) {
+    let map: BTreeMap<_, _> = keys.iter().map(|&n| (vec![n], vec![n, n])).collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for &n in keys.iter() {
+        lsm.insert(&[n], &[n, n]);
+    }
+
+    let lookups: Vec<_> = lookups.iter().map(std::slice::from_ref).collect();
+    let expected = lookups.iter().map(|&key| map.get(key).cloned());
+    assert_equal(expected, lsm.get_many(&lookups));
+
+    for key in lookups {
+        assert_eq!(map.get(key).map(Vec::as_slice), lsm.get(key).as_deref());
+    }
+}
+
+#[quickcheck]
+fn key_scans_match_btree_map(keys: Vec<u8>, start: u8, end: u8) {
+    let map: BTreeMap<_, _> = keys.iter().map(|&n| (vec![n], vec![n; 1024])).collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for &n in keys.iter() {
+        lsm.insert(&[n], &[n; 1024]);
+    }
+
+    assert_equal(map.keys().cloned(), lsm.keys());
+    assert_equal(map.keys().rev().cloned(), lsm.keys().rev());
+
+    if start <= end {
+        let (start, end) = ([start], [end]);
+        let range = start.as_ref()..end.as_ref();
+        assert_equal(
+            map.range(start.to_vec()..end.to_vec())
+                .map(|(k, _)| k.clone()),
+            lsm.range_keys(range.clone()),
+        );
+        assert_eq!(
+            map.range(start.to_vec()..end.to_vec()).count(),
+            lsm.count_range(range)
+        );
+    }
+}
+
+#[test]
+fn lengths_are_counted_or_estimated() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    for n in 0u32..16 {
+        lsm.insert(n.to_be_bytes().as_ref(), &[0; 64]);
+    }
+    assert_eq!(lsm.len(), 16);
+    assert_eq!(lsm.approximate_len(), 16);
+
+    for n in 16u32..4096 {
+        lsm.insert(n.to_be_bytes().as_ref(), &[0; 64]);
+    }
+    assert_eq!(lsm.len(), 4096);
+    lsm.flush().unwrap();
+
+    // pages are not filled to the brim and entries carry some overhead, so allow a factor of two
+    let estimate = lsm.approximate_len();
+    assert!((2048..=8192).contains(&estimate), "{estimate}");
+
+    let (middle, end) = (2048u32.to_be_bytes(), 4096u32.to_be_bytes());
+    let whole = lsm.approximate_size(..);
+    let half = lsm.approximate_size(middle.as_ref()..end.as_ref());
+    assert!(whole > 0 && half > 0 && half < whole);
+}
+
+#[quickcheck]
+fn pages_cover_the_range_despite_writes(keys: Vec<u8>, limit: u8, reverse: bool) {
+    let limit = limit as usize % 8 + 1;
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for &n in keys.iter() {
+        lsm.insert(&[n], &[n]);
+    }
+
+    let mut read = Vec::new();
+    let mut inserted = Vec::new();
+    let mut token = None;
+    loop {
+        let page = match reverse {
+            false => lsm.page(.., limit, token),
+            true => lsm.page_rev(.., limit, token),
+        };
+        assert!(page.items.len() <= limit);
+
+        read.extend(page.items.into_iter().map(|(key, _)| key));
+        let Some(next) = page.next else { break };
+        token = Some(crate::Token::from_bytes(&next.to_bytes()).unwrap());
+
+        // removing what was read must not disturb the scan, and a key right after the last one read
+        // only shows up in a forward scan
+        let last = read.last().unwrap().clone();
+        let after = [last.as_slice(), &[0]].concat();
+        lsm.remove(&last);
+        lsm.insert(&after, &after);
+        inserted.push(after);
+    }
+
+    let mut expected: Vec<_> = keys.into_iter().map(|n| vec![n]).collect();
+    if reverse == false {
+        expected.extend(inserted);
+    }
+    expected.sort();
+    expected.dedup();
+    if reverse {
+        expected.reverse();
+    }
+
+    assert_eq!(expected, read);
+}
+
+#[quickcheck]
+fn draining_and_extracting_match_btree_map(keys: Vec<u16>, start: u16, taken: u8) {
+    let mut map: BTreeMap<_, _> = keys
+        .iter()
+        .map(|n| (n.to_be_bytes().to_vec(), vec![]))
+        .collect();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for n in keys.iter() {
+        lsm.insert(n.to_be_bytes().as_ref(), &[]);
+    }
+
+    // only every other key is extracted, and only as far as the iterator gets
+    let odd = |key: &[u8]| key[1] % 2 == 1;
+    let extracted: Vec<_> = lsm
+        .extract_if(.., |key, _| odd(key))
+        .take(taken as usize)
+        .collect();
+    let expected: Vec<_> = map
+        .iter()
+        .filter(|(key, _)| odd(key))
+        .take(taken as usize)
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    for (key, _) in expected.iter() {
+        map.remove(key);
+    }
+    assert_eq!(expected, extracted);
+    assert_equal(map.clone(), lsm.iter());
+
+    // the whole range goes, even though the iterator is dropped early
+    let start = start.to_be_bytes();
+    let drained: Vec<_> = lsm.drain(start.as_ref()..).take(taken as usize).collect();
+    let rest = map.split_off(start.as_ref());
+    assert_equal(rest.into_iter().take(taken as usize), drained);
+    assert_equal(map, lsm.iter());
+}
+
+#[test]
+fn split_off_streams_into_another_file() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for n in 0u32..1024 {
+        lsm.insert(n.to_be_bytes().as_ref(), n.to_le_bytes().as_ref());
+    }
+
+    let other = temp_file::TempFile::new().unwrap();
+    let split = 300u32.to_be_bytes();
+    let tail = lsm.split_off_to_path(&split, other.path().to_str().unwrap());
+
+    let entry = |n: u32| (n.to_be_bytes().to_vec(), n.to_le_bytes().to_vec());
+    assert_equal(lsm.iter(), (0..300).map(entry));
+    assert_equal(tail.iter(), (300..1024).map(entry));
+
+    // an existing database is left alone rather than merged into
+    let split = 100u32.to_be_bytes();
+    let path = other.path().to_str().unwrap();
+    let existing = lsm.try_split_off_to_path(&split, path);
+    assert!(matches!(existing, Err(crate::Error::Misuse)));
+    assert_equal(lsm.iter(), (0..300).map(entry));
+    assert_equal(tail.iter(), (300..1024).map(entry));
+}
+
+#[test]
+fn split_off_keeps_what_other_connections_write() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut lsm = crate::Map::new(path).unwrap();
+    lsm.extend((0u32..16384).map(|n| (n.to_be_bytes(), n.to_le_bytes())));
+
+    let other = temp_file::TempFile::new().unwrap();
+    let mut tail = crate::Map::new(other.path().to_str().unwrap()).unwrap();
+
+    // a second connection keeps writing throughout, wherever the split has got to
+    let done = AtomicBool::new(false);
+    let written = std::thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            let mut writer = crate::Map::new(path).unwrap();
+            let mut written = Vec::new();
+            for n in 1u32..u16::MAX as u32 {
+                if done.load(Ordering::Relaxed) {
+                    break;
+                }
+                let key = (n << 16).to_be_bytes();
+                if writer.try_insert(&key, b"written").is_ok() {
+                    written.push(key);
+                }
+                std::thread::yield_now();
+            }
+            written
+        });
+
+        // the split takes up where it left off whenever the writer holds the lock
+        let split = 8192u32.to_be_bytes();
+        while let Err(error) = lsm.try_split_off_into(&split, &mut tail) {
+            assert_eq!(error, crate::Error::Busy);
+        }
+        done.store(true, Ordering::Relaxed);
+        writer.join().unwrap()
+    });
+
+    for key in written {
+        assert!(lsm.contains_key(&key) || tail.contains_key(&key));
+    }
+}
+
+#[test]
+fn append_map_keeps_what_other_connections_write() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut other = crate::Map::new(path).unwrap();
+    other.extend((0u32..16384).map(|n| (n.to_be_bytes(), n.to_le_bytes())));
+
+    let target = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(target.path().to_str().unwrap()).unwrap();
+
+    // a second connection keeps writing throughout, wherever the move has got to
+    let done = AtomicBool::new(false);
+    let written = std::thread::scope(|scope| {
+        let writer = scope.spawn(|| {
+            let mut writer = crate::Map::new(path).unwrap();
+            let mut written = Vec::new();
+            for n in 1u32..u16::MAX as u32 {
+                if done.load(Ordering::Relaxed) {
+                    break;
+                }
+                let key = (n << 16).to_be_bytes();
+                if writer.try_insert(&key, b"written").is_ok() {
+                    written.push(key);
+                }
+                std::thread::yield_now();
+            }
+            written
+        });
+
+        while let Err(error) = lsm.try_append_map(&mut other) {
+            assert_eq!(error, crate::Error::Busy);
+        }
+        done.store(true, Ordering::Relaxed);
+        writer.join().unwrap()
+    });
+
+    for key in written {
+        assert!(lsm.contains_key(&key) || other.contains_key(&key));
+    }
+}
+
+#[quickcheck]
+fn extending_matches_btree_map(sorted: Vec<u16>, unsorted: Vec<(u8, u8)>) {
+    let mut map = BTreeMap::<Vec<u8>, Vec<u8>>::new();
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    let mut sorted: Vec<_> = sorted.iter().map(|n| (n.to_be_bytes(), [0])).collect();
+    sorted.sort();
+    sorted.dedup();
+    map.extend(sorted.iter().map(|(k, v)| (k.to_vec(), v.to_vec())));
+    lsm.extend(sorted);
+
+    // later duplicates win, as they do for a BTreeMap
+    let unsorted: Vec<_> = unsorted.iter().map(|&(k, v)| ([k], [v])).collect();
+    map.extend(unsorted.iter().map(|(k, v)| (k.to_vec(), v.to_vec())));
+    lsm.extend(unsorted);
+
+    let other_file = temp_file::TempFile::new().unwrap();
+    let mut other = crate::Map::new(other_file.path().to_str().unwrap()).unwrap();
+    let batch = map.iter().map(|(k, v)| ([k.as_slice(), b"!"].concat(), v));
+    other.write(batch.collect()).unwrap();
+
+    let expected: Vec<_> = other.iter().collect();
+    map.extend(expected);
+    lsm.append_map(&mut other);
+
+    assert!(other.is_empty());
+    assert_equal(map, lsm.iter());
+}
+
+#[test]
+fn background_worker_keeps_the_map_intact() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    let options = crate::WorkerOptions::new()
+        .flush(16)
+        .interval(std::time::Duration::from_millis(1))
+        .clone();
+    lsm.start_worker(&options).unwrap();
+    assert!(lsm.start_worker(&options).is_err());
+    assert!(lsm.settings().unwrap().auto_work == false);
+
+    let entry = |n: u32| (n.to_be_bytes().to_vec(), n.to_le_bytes().to_vec());
+    for n in 0..4096u32 {
+        lsm.insert(&n.to_be_bytes(), &n.to_le_bytes());
+        if n == 2048 {
+            lsm.worker().unwrap().pause();
+            assert!(lsm.worker().unwrap().is_paused());
+            lsm.worker().unwrap().resume();
+        }
+    }
+
+    lsm.stop_worker().unwrap();
+    assert!(lsm.worker().is_none());
+    assert!(lsm.settings().unwrap().auto_work);
+    assert_equal(lsm.iter(), (0..4096).map(entry));
+}
+
+#[test]
+fn compacting_keeps_the_map_intact() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::options()
+        .auto_work(false)
+        .open(file.path().to_str().unwrap())
+        .unwrap();
+
+    for round in 0..4u32 {
+        for n in 0..256u32 {
+            lsm.insert(&n.to_be_bytes(), &(n * round).to_le_bytes());
+        }
+        lsm.flush().unwrap();
+    }
+    assert!(lsm.work(0, 1024).is_err());
+
+    lsm.compact_fully().unwrap();
+    let segments: usize = lsm
+        .tree
+        .db_structure()
+        .unwrap()
+        .iter()
+        .map(|level| level.segments.len())
+        .sum();
+    assert!(segments <= 1);
+
+    let entry = |n: u32| (n.to_be_bytes().to_vec(), (n * 3).to_le_bytes().to_vec());
+    assert_equal(lsm.iter(), (0..256).map(entry));
+}
+
+#[test]
+fn stats_follow_writes() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    for n in 0..1024u32 {
+        lsm.insert(&n.to_be_bytes(), &n.to_le_bytes());
+    }
+    let before = lsm.stats().unwrap();
+    assert!(before.tree_size > 0);
+
+    lsm.flush().unwrap();
+    let after = lsm.stats().unwrap();
+    assert!(after.pages_written > before.pages_written);
+    assert!(after.tree_size < before.tree_size);
+}
+
+#[test]
+fn structure_lists_flushed_segments() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    for n in 0..1024u32 {
+        lsm.insert(&n.to_be_bytes(), &n.to_le_bytes());
+    }
+    lsm.flush().unwrap();
+
+    let structure = lsm.structure().unwrap();
+    let segments: Vec<_> = structure
+        .levels
+        .iter()
+        .flat_map(|level| &level.segments)
+        .collect();
+    assert!(segments.is_empty() == false);
+    for segment in segments {
+        let first = segment.pages.first().map(|pages| *pages.start());
+        assert_eq!(first, Some(segment.first));
+    }
+
+    let diagram = structure.to_string();
+    assert!(diagram.starts_with("levels\n"));
+    assert!(diagram.contains("segment"));
+}
+
+#[test]
+fn structure_lists_segments_without_pages() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::options()
+        .auto_work(false)
+        .open(file.path().to_str().unwrap())
+        .unwrap();
+
+    // merging segments whose keys all cancel out leaves levels with nothing written to them
+    for n in 0..256u32 {
+        lsm.insert(&n.to_be_bytes(), &n.to_le_bytes());
+    }
+    lsm.flush().unwrap();
+    lsm.clear();
+    lsm.flush().unwrap();
+
+    loop {
+        let structure = lsm.structure().unwrap();
+        for segment in structure.levels.iter().flat_map(|level| &level.segments) {
+            assert!(segment.first != 0 || segment.pages.is_empty());
+        }
+
+        if lsm.work(2, 1).unwrap() == 0 {
+            break;
+        }
+    }
+    assert!(lsm.is_empty());
+}
+
+#[cfg(feature = "debug-introspection")]
+#[test]
+fn debug_dumps_flushed_pages() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+
+    for n in 0..1024u32 {
+        lsm.insert(&n.to_be_bytes(), &n.to_le_bytes());
+    }
+    lsm.flush().unwrap();
+
+    let structure = lsm.structure().unwrap();
+    let segment = &structure.levels[0].segments[0];
+    let first = segment.first;
+
+    let pages = lsm.debug().array_pages(first).unwrap();
+    assert_eq!(pages.first(), Some(&first));
+    assert!(lsm.debug().page(first, true).unwrap().is_empty() == false);
+    assert!(lsm.debug().page(first, false).unwrap().is_empty() == false);
+    lsm.debug().freelist().unwrap();
+}
+
+#[test]
+fn reads_see_commits_from_other_connections() {
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut writer = crate::Map::new(path).unwrap();
+    writer.insert(b"a", b"1");
+
+    let reader = crate::Map::new(path).unwrap();
+    let read_only = crate::Map::open_read_only(path).unwrap();
+    assert_eq!(
+        reader.get_key_value(b"a"),
+        Some((b"a".to_vec(), b"1".to_vec()))
     );
+    assert!(read_only.contains_key(b"a"));
+    assert!(reader.get(b"b").is_none());
+
+    writer.insert(b"a", b"2");
+    writer.insert(b"b", b"3");
+
+    assert_eq!(
+        reader.get_key_value(b"a"),
+        Some((b"a".to_vec(), b"2".to_vec()))
+    );
+    assert!(reader.get(b"b").is_some());
+    assert!(read_only.contains_key(b"b"));
+    assert_eq!(reader.len(), 2);
+}
+
+#[test]
+fn snapshots_read_as_of_their_last_refresh() {
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    let mut writer = crate::Map::new(path).unwrap();
+    writer.insert(b"a", b"1");
+
+    let reader = crate::Map::new(path).unwrap();
+    {
+        let snapshot = reader.snapshot();
+        assert_eq!(&*snapshot.get(b"a").unwrap(), b"1");
+
+        writer.insert(b"a", b"2");
+        assert_eq!(&*snapshot.get(b"a").unwrap(), b"1");
+        assert!(snapshot.contains_key(b"b") == false);
+
+        writer.insert(b"b", b"3");
+        snapshot.refresh();
+        assert_eq!(&*snapshot.get(b"a").unwrap(), b"2");
+        assert!(snapshot.contains_key(b"b"));
+    }
+
+    writer.insert(b"a", b"4");
+    assert_eq!(&*reader.get(b"a").unwrap(), b"4");
+}
+
+#[test]
+fn removals_succeed_alongside_a_worker() {
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    for n in 0..4096u32 {
+        lsm.insert(&n.to_be_bytes(), &n.to_le_bytes());
+    }
+
+    let options = crate::WorkerOptions::new()
+        .flush(4)
+        .interval(std::time::Duration::from_millis(1))
+        .clone();
+    lsm.start_worker(&options).unwrap();
+
+    for n in 0..1024u32 {
+        let entry = (n.to_be_bytes().to_vec(), n.to_le_bytes().to_vec());
+        assert_eq!(lsm.pop_first(), Some(entry));
+
+        let n = 4095 - n;
+        assert_eq!(lsm.remove(&n.to_be_bytes()), Some(n.to_le_bytes().to_vec()));
+        lsm.entry(&(n - 1).to_be_bytes()).and_modify(|value| {
+            *value = std::borrow::Cow::Owned(b"modified".to_vec());
+        });
+    }
+
+    lsm.stop_worker().unwrap();
+    assert_eq!(lsm.len(), 2048);
+    assert_eq!(
+        lsm.get(&3071u32.to_be_bytes()).unwrap().as_ref(),
+        b"modified"
+    );
+}
+
+#[quickcheck]
+fn owned_iteration_matches_btree_map(count: u16, directions: Vec<bool>) {
+    let count = count as u32 % 2048; // enough for a few batches from each end
+    let entry = |n: u32| (n.to_be_bytes().to_vec(), n.to_le_bytes().to_vec());
+
+    let file = temp_file::TempFile::new().unwrap();
+    let mut lsm = crate::Map::new(file.path().to_str().unwrap()).unwrap();
+    lsm.extend((0..count).map(entry));
+
+    let mut expected = (0..count).map(entry);
+    let mut actual = lsm.into_iter();
+
+    let alternating = (0..=count).map(|n| n % 3 == 0);
+    for forward in directions.into_iter().chain(alternating) {
+        match forward {
+            true => assert_eq!(expected.next(), actual.next()),
+            false => assert_eq!(expected.next_back(), actual.next_back()),
+        }
+    }
+}
+
+#[test]
+fn storage_errors_are_returned_rather_than_raised() {
+    use crate::Error::*;
+
+    let missing = std::env::temp_dir()
+        .join("lsm1-missing-directory")
+        .join("db");
+    assert!(crate::Map::new(missing.to_str().unwrap()).is_err());
+
+    let file = temp_file::TempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+
+    // a second connection cannot write while the first has a write transaction open
+    let mut holder = crate::Map::new(path).unwrap();
+    let mut other = crate::Map::new(path).unwrap();
+    let mut tx = holder.transaction().unwrap();
+    tx.insert(b"a", b"1");
+
+    assert_eq!(other.try_insert(b"b", b"2"), Err(Busy));
+    assert_eq!(other.try_remove_range(..), Err(Busy));
+    assert_eq!(other.try_get_key_value(b"b"), Ok(None));
+
+    let inserted = other.entry(b"b").try_or_insert(b"2");
+    assert_eq!(inserted.map(|value| value.to_vec()), Err(Busy));
+
+    tx.commit().unwrap();
+    assert_eq!(other.try_insert(b"b", b"2"), Ok(None));
+    assert_equal(holder.keys(), [b"a".to_vec(), b"b".to_vec()]);
 }
@@ -0,0 +1,164 @@
+use crate::{Error, Map, Tree};
+
+use lsm_ext::*;
+
+use std::num::NonZeroU32;
+
+/// A write transaction on a `Map`.
+///
+/// The transaction dereferences to the `Map` it was started on, so the whole map API is available
+/// through it. Its changes become visible to other connections, and durable, only once
+/// [`commit`](Transaction::commit) succeeds; they are rolled back if the transaction is dropped
+/// instead, including when a panic unwinds through it.
+pub struct Transaction<'m> {
+    map: &'m mut Map,
+    level: NonZeroU32,
+    open: bool,
+}
+
+impl<'m> Transaction<'m> {
+    pub(crate) fn begin(map: &'m mut Map) -> Result<Self, Error> {
+        let level = map.tree.begin()?;
+
+        Ok(Transaction {
+            map,
+            level,
+            open: true,
+        })
+    }
+
+    /// Makes the changes made within the transaction permanent.
+    ///
+    /// The transaction is rolled back if committing fails.
+    pub fn commit(mut self) -> Result<(), Error> {
+        self.map.tree.commit(self.level)?;
+        self.open = false;
+
+        Ok(())
+    }
+
+    /// Discards the changes made within the transaction.
+    pub fn rollback(mut self) -> Result<(), Error> {
+        self.open = false;
+        Ok(self.map.tree.rollback(self.level)?)
+    }
+
+    /// Starts a savepoint: a transaction nested inside of this one that can be released into it, or
+    /// rolled back on its own.
+    ///
+    /// The savepoint borrows the transaction, so it has to be finished before its parent can be.
+    ///
+    /// ```compile_fail
+    /// # fn import(map: &mut lsm1::Map) -> Result<(), lsm1::Error> {
+    /// let mut tx = map.transaction()?;
+    /// let savepoint = tx.savepoint()?;
+    /// tx.commit()?; // `savepoint` is still open
+    /// savepoint.release()
+    /// # }
+    /// ```
+    pub fn savepoint(&mut self) -> Result<Savepoint<'_>, Error> {
+        Ok(Savepoint(Transaction::begin(self.map)?))
+    }
+}
+
+/// A transaction nested inside of another, as started by [`Transaction::savepoint`].
+///
+/// Like a transaction, a savepoint is rolled back if it is dropped without being released.
+pub struct Savepoint<'t>(Transaction<'t>);
+
+impl<'t> Savepoint<'t> {
+    /// Merges the changes made since the savepoint into the enclosing transaction.
+    ///
+    /// They are still discarded if that transaction is rolled back.
+    pub fn release(self) -> Result<(), Error> {
+        self.0.commit()
+    }
+
+    /// Discards the changes made since the savepoint, leaving the enclosing transaction as it was.
+    pub fn rollback(self) -> Result<(), Error> {
+        self.0.rollback()
+    }
+}
+
+impl<'t> std::ops::Deref for Savepoint<'t> {
+    type Target = Transaction<'t>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Transaction<'t> {
+        &self.0
+    }
+}
+
+impl<'t> std::ops::DerefMut for Savepoint<'t> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Transaction<'t> {
+        &mut self.0
+    }
+}
+
+impl<'m> std::ops::Deref for Transaction<'m> {
+    type Target = Map;
+
+    #[inline(always)]
+    fn deref(&self) -> &Map {
+        self.map
+    }
+}
+
+impl<'m> std::ops::DerefMut for Transaction<'m> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Map {
+        self.map
+    }
+}
+
+impl<'m> Drop for Transaction<'m> {
+    fn drop(&mut self) {
+        if self.open {
+            let _ = self.map.tree.rollback(self.level);
+        }
+    }
+}
+
+impl Tree {
+    /// Opens a transaction nested inside of any that are already open, returning its level.
+    pub fn begin(&self) -> Result<NonZeroU32, lsm_ext::Error> {
+        let level = NonZeroU32::MIN.saturating_add(self.depth.get());
+
+        self.reset();
+        unsafe { lsm_begin(self.db, level).ok()? };
+
+        self.depth.set(level.get());
+        Ok(level)
+    }
+
+    pub fn commit(&self, level: NonZeroU32) -> Result<(), lsm_ext::Error> {
+        let outer = level.get() - 1;
+
+        self.reset(); // a cursor cached within the transaction must not outlive it
+        unsafe { lsm_commit(self.db, outer).ok()? };
+
+        self.depth.set(outer);
+        Ok(())
+    }
+
+    pub fn rollback(&self, level: NonZeroU32) -> Result<(), lsm_ext::Error> {
+        let outer = level.get() - 1;
+        self.reset();
+
+        // rolling back to a level leaves that transaction open, so nested ones are closed by
+        // committing what is left of them (nothing) into their parent
+        unsafe {
+            match outer {
+                0 => lsm_rollback(self.db, 0).ok()?,
+                _ => {
+                    lsm_rollback(self.db, level.get()).ok()?;
+                    lsm_commit(self.db, outer).ok()?;
+                }
+            }
+        }
+
+        self.depth.set(outer);
+        Ok(())
+    }
+}
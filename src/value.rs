@@ -0,0 +1,68 @@
+use lsm_ext::*;
+
+use crate::range::Bound;
+
+/// A reference to a value stored in a `Map`.
+///
+/// The bytes are read in place from the cursor that found them, so the guard keeps that cursor open and
+/// the map borrowed; it dereferences to `[u8]` and can be copied out with `to_vec()` when it has to outlive them.
+///
+/// ```compile_fail
+/// # fn update(map: &mut lsm1::Map) {
+/// let value = map.get(b"key").unwrap();
+/// map.insert(b"key", b"other"); // `value` still points into the database
+/// println!("{value:?}");
+/// # }
+/// ```
+pub struct ValueRef<'c> {
+    bound: Bound<'c>,
+    ptr: *const u8,
+    len: usize,
+}
+
+impl<'c> ValueRef<'c> {
+    /// Takes over a bound that is positioned on an entry.
+    pub(crate) fn new_in(bound: Bound<'c>) -> Result<Self, Error> {
+        let val = bound.val()?;
+        let (ptr, len) = (val.as_ptr(), val.len());
+
+        Ok(ValueRef { bound, ptr, len })
+    }
+
+    #[inline]
+    /// Returns the key the value is stored under.
+    pub fn key(&self) -> &[u8] {
+        self.bound.key().unwrap()
+    }
+}
+
+impl<'c> std::ops::Deref for ValueRef<'c> {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the cursor is neither moved nor outlived by `self`, and writing to the map requires
+        // a mutable borrow that `'c` rules out
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'c> AsRef<[u8]> for ValueRef<'c> {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<'c> std::fmt::Debug for ValueRef<'c> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<'c> PartialEq<[u8]> for ValueRef<'c> {
+    #[inline]
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{Error, Map, OpenOptions, Settings, Tree};
+
+use lsm_ext::Config;
+
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How a background worker maintains the database, as passed to [`Map::start_worker`].
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use lsm1::{Map, WorkerOptions};
+///
+/// let mut map = Map::new("example.db")?;
+/// map.start_worker(WorkerOptions::new().work(4096).checkpoint(Duration::from_secs(5)))?;
+/// # Ok::<(), lsm1::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct WorkerOptions {
+    work: u32,
+    merge: u32,
+    flush: u32,
+    checkpoint: Duration,
+    interval: Duration,
+}
+
+impl Default for WorkerOptions {
+    fn default() -> Self {
+        WorkerOptions {
+            work: 1024,
+            merge: 4,
+            flush: 1024,
+            checkpoint: Duration::from_secs(1),
+            interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl WorkerOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The number of KB merged at a time by `lsm_work`.
+    pub fn work(&mut self, kilobytes: u32) -> &mut Self {
+        self.work = kilobytes;
+        self
+    }
+
+    /// The minimum number of segments that are merged together. At least 1.
+    pub fn merge(&mut self, segments: u32) -> &mut Self {
+        self.merge = segments;
+        self
+    }
+
+    /// The size of the in-memory tree, in KB, at which the worker flushes it to disk.
+    pub fn flush(&mut self, kilobytes: u32) -> &mut Self {
+        self.flush = kilobytes;
+        self
+    }
+
+    /// How often the worker checkpoints the database.
+    pub fn checkpoint(&mut self, interval: Duration) -> &mut Self {
+        self.checkpoint = interval;
+        self
+    }
+
+    /// How long the worker sleeps once it has run out of work.
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+}
+
+/// A background thread maintaining a `Map`’s database through a connection of its own, as started
+/// by [`Map::start_worker`].
+///
+/// The worker merges segments, flushes the in-memory tree and checkpoints the database, so that
+/// writers do not have to. It is stopped when the map is closed or dropped.
+pub struct Worker {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+    auto_work: i32, // the map’s own setting, restored once the worker stops
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    wake: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    paused: bool,
+    stopped: bool,
+}
+
+impl Worker {
+    fn start(path: &str, settings: &Settings, options: &WorkerOptions) -> Result<Self, Error> {
+        if options.merge == 0 {
+            return Err(Error::Misuse);
+        }
+
+        // the worker connection shares the map’s view of the file, but never works inline
+        let mut open = OpenOptions::new();
+        open.auto_work(false)
+            .safety(settings.safety)
+            .mmap(settings.mmap)
+            .use_log(settings.use_log)
+            .multiple_processes(settings.multiple_processes);
+
+        let shared = Arc::new(Shared::default());
+        let (path, options) = (path.to_owned(), options.clone());
+        let (opened, started) = mpsc::channel::<Result<(), Error>>();
+
+        let thread = {
+            let shared = shared.clone();
+            std::thread::Builder::new()
+                .name("lsm1-worker".into())
+                .spawn(move || match Tree::open(&path, &open) {
+                    Ok(tree) => {
+                        let _ = opened.send(Ok(()));
+                        run(&tree, &shared, &options)
+                    }
+                    Err(error) => {
+                        let _ = opened.send(Err(error.into()));
+                        Ok(())
+                    }
+                })
+                .map_err(|_| Error::Error)?
+        };
+
+        started.recv().map_err(|_| Error::Error)??;
+        Ok(Worker {
+            shared,
+            thread: Some(thread),
+            auto_work: settings.auto_work as i32,
+        })
+    }
+
+    /// Suspends maintenance until [`resume`](Worker::resume) is called.
+    pub fn pause(&self) {
+        self.shared.state.lock().unwrap().paused = true;
+    }
+
+    /// Resumes maintenance after [`pause`](Worker::pause).
+    pub fn resume(&self) {
+        self.shared.state.lock().unwrap().paused = false;
+        self.shared.wake.notify_all();
+    }
+
+    /// Returns `true` if the worker is paused.
+    pub fn is_paused(&self) -> bool {
+        self.shared.state.lock().unwrap().paused
+    }
+
+    /// Stops the thread, returning the error that made it stop early, if any.
+    fn stop(&mut self) -> Result<(), Error> {
+        self.shared.state.lock().unwrap().stopped = true;
+        self.shared.wake.notify_all();
+
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(Error::Error), // the thread panicked
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// Keeps maintaining the database until the worker is stopped.
+fn run(tree: &Tree, shared: &Shared, options: &WorkerOptions) -> Result<(), Error> {
+    let mut checkpointed = Instant::now();
+    let mut idle = false;
+
+    loop {
+        {
+            let mut state = shared.state.lock().unwrap();
+            if idle && state.stopped == false {
+                state = shared.wake.wait_timeout(state, options.interval).unwrap().0;
+            }
+            while state.paused && state.stopped == false {
+                state = shared.wake.wait(state).unwrap();
+            }
+            if state.stopped {
+                return Ok(());
+            }
+        }
+
+        let result = (|| -> Result<bool, lsm_ext::Error> {
+            let (_, size) = tree.tree_size()?;
+            if size as u32 >= options.flush {
+                tree.flush()?;
+            }
+
+            let written = tree.work(options.merge, options.work)?;
+
+            if checkpointed.elapsed() >= options.checkpoint {
+                tree.checkpoint()?;
+                checkpointed = Instant::now();
+            }
+
+            Ok(written > 0)
+        })();
+
+        idle = match result {
+            Ok(worked) => worked == false,
+            Err(lsm_ext::Error::Busy) => true, // another connection holds the lock; try again later
+            Err(error) => return Err(error.into()),
+        };
+    }
+}
+
+impl Map {
+    /// Starts a background thread that maintains the database, with a connection of its own.
+    ///
+    /// The map stops merging segments as part of its own writes while the worker runs. Fails with
+    /// [`Error::Misuse`] if a worker is running already.
+    pub fn start_worker(&mut self, options: &WorkerOptions) -> Result<(), Error> {
+        if self.worker.is_some() {
+            return Err(Error::Misuse);
+        }
+
+        let settings = self.settings()?;
+        let worker = Worker::start(self.tree.path(), &settings, options)?;
+
+        self.tree.config(Config::AutoWork, 0)?;
+        self.worker = Some(worker);
+        Ok(())
+    }
+
+    /// Returns the background worker, if one is running.
+    pub fn worker(&self) -> Option<&Worker> {
+        self.worker.as_ref()
+    }
+
+    /// Stops the background worker, returning the error that made it stop early, if any.
+    ///
+    /// The map goes back to merging segments as part of its writes, if it did before the worker
+    /// was started.
+    pub fn stop_worker(&mut self) -> Result<(), Error> {
+        let Some(mut worker) = self.worker.take() else {
+            return Ok(());
+        };
+
+        let result = worker.stop();
+        self.tree.config(Config::AutoWork, worker.auto_work)?;
+        result
+    }
+}